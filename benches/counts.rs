@@ -0,0 +1,34 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use schemamama::{InMemoryAdapter, Migration, Migrator, Version};
+
+struct BenchMigration(Version);
+
+impl Migration for BenchMigration {
+    fn version(&self) -> Version { self.0 }
+    fn description(&self) -> String { "bench migration".into() }
+}
+
+fn migrator() -> Migrator<InMemoryAdapter> {
+    // Half of the registered migrations are applied:
+    let versions = (0..1000).filter(|v| v % 2 == 0).map(Version).collect();
+    let mut migrator = Migrator::new(InMemoryAdapter::with_versions(versions));
+    for version in 0..1000 {
+        migrator.register(Box::new(BenchMigration(Version(version)))).unwrap();
+    }
+    migrator
+}
+
+fn counts(c: &mut Criterion) {
+    let mut migrator = migrator();
+    c.bench_function("pending_versions().len()", |b| {
+        b.iter(|| migrator.pending_versions().unwrap().len())
+    });
+    c.bench_function("count_pending()", |b| b.iter(|| migrator.count_pending().unwrap()));
+    c.bench_function("applied_versions().len()", |b| {
+        b.iter(|| migrator.applied_versions().unwrap().len())
+    });
+    c.bench_function("count_applied()", |b| b.iter(|| migrator.count_applied().unwrap()));
+}
+
+criterion_group!(benches, counts);
+criterion_main!(benches);
@@ -0,0 +1,57 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use schemamama::{InMemoryAdapter, Migration, Migrator, RawVersion, Version};
+use std::collections::BTreeMap;
+
+const MIGRATIONS: RawVersion = 500;
+
+struct BenchMigration(Version);
+
+impl Migration for BenchMigration {
+    fn version(&self) -> Version { self.0 }
+    fn description(&self) -> String { "bench migration".into() }
+}
+
+// Registration order that is neither ascending nor descending:
+fn versions() -> impl Iterator<Item = Version> {
+    (0..MIGRATIONS).map(|v| Version((v * 7919) % MIGRATIONS))
+}
+
+fn storage(c: &mut Criterion) {
+    let map: BTreeMap<Version, Box<dyn Migration>> =
+        versions().map(|v| (v, Box::new(BenchMigration(v)) as Box<dyn Migration>)).collect();
+    let mut vec: Vec<(Version, Box<dyn Migration>)> =
+        versions().map(|v| (v, Box::new(BenchMigration(v)) as Box<dyn Migration>)).collect();
+    vec.sort_by_key(|&(v, _)| v);
+
+    c.bench_function("BTreeMap iteration", |b| {
+        b.iter(|| map.values().map(|m| m.version().get()).sum::<RawVersion>())
+    });
+    c.bench_function("sorted Vec iteration", |b| {
+        b.iter(|| vec.iter().map(|(_, m)| m.version().get()).sum::<RawVersion>())
+    });
+    c.bench_function("BTreeMap lookup", |b| {
+        b.iter(|| versions().map(|v| map[&black_box(v)].version().get()).sum::<RawVersion>())
+    });
+    c.bench_function("sorted Vec lookup", |b| {
+        b.iter(|| {
+            versions().map(|v| {
+                let index = vec.binary_search_by_key(&black_box(v), |&(v, _)| v).unwrap();
+                vec[index].1.version().get()
+            }).sum::<RawVersion>()
+        })
+    });
+
+    let mut migrator = Migrator::new(InMemoryAdapter::new());
+    for version in versions() {
+        migrator.register(Box::new(BenchMigration(version))).unwrap();
+    }
+    c.bench_function("Migrator up and down", |b| {
+        b.iter(|| {
+            migrator.up(None).unwrap();
+            migrator.down(None).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, storage);
+criterion_main!(benches);
@@ -0,0 +1,82 @@
+use alloc::collections::BTreeSet;
+use core::convert::Infallible;
+
+use crate::{Adapter, MarkableAdapter, Migration, Version};
+
+/// An adapter that keeps the versions of the applied migrations in memory, for use in tests and
+/// examples. Any migration type can be registered with it, as its `MigrationType` is
+/// `dyn Migration`, and it never fails.
+///
+/// ## Example
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate schemamama;
+/// use schemamama::{InMemoryAdapter, Migrator, Version};
+///
+/// struct CreateUsers;
+/// migration!(CreateUsers, 10, "create users");
+///
+/// # fn main() {
+/// let mut migrator = Migrator::new(InMemoryAdapter::new());
+/// migrator.register(Box::new(CreateUsers)).unwrap();
+/// migrator.up(None).unwrap();
+/// assert!(migrator.adapter().is_applied(Version(10)));
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InMemoryAdapter {
+    versions: BTreeSet<Version>,
+}
+
+impl InMemoryAdapter {
+    /// Create an adapter with no applied migrations.
+    pub fn new() -> InMemoryAdapter {
+        InMemoryAdapter::default()
+    }
+
+    /// Create an adapter with the migrations of the given versions already applied.
+    pub fn with_versions(versions: BTreeSet<Version>) -> InMemoryAdapter {
+        InMemoryAdapter { versions }
+    }
+
+    /// Returns the set of the versions of all of the currently applied migrations.
+    pub fn versions(&self) -> &BTreeSet<Version> {
+        &self.versions
+    }
+
+    /// Returns true if the migration of the given version is currently applied.
+    pub fn is_applied(&self, version: Version) -> bool {
+        self.versions.contains(&version)
+    }
+}
+
+impl Adapter for InMemoryAdapter {
+    type MigrationType = dyn Migration;
+    type Error = Infallible;
+
+    fn current_schema_version(&mut self) -> Result<Option<Version>, Infallible> {
+        Ok(self.versions.iter().next_back().copied())
+    }
+
+    fn migrated_versions(&mut self) -> Result<BTreeSet<Version>, Infallible> {
+        Ok(self.versions.clone())
+    }
+
+    fn apply_migration(&mut self, migration: &dyn Migration) -> Result<(), Infallible> {
+        self.versions.insert(migration.version());
+        Ok(())
+    }
+
+    fn revert_migration(&mut self, migration: &dyn Migration) -> Result<(), Infallible> {
+        self.versions.remove(&migration.version());
+        Ok(())
+    }
+}
+
+impl MarkableAdapter for InMemoryAdapter {
+    fn mark_applied(&mut self, version: Version) -> Result<(), Infallible> {
+        self.versions.insert(version);
+        Ok(())
+    }
+}
@@ -0,0 +1,3 @@
+//! Adapters that are provided by this crate.
+
+pub mod memory;
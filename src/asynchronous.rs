@@ -0,0 +1,226 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::future::Future;
+
+use super::registry::Registry;
+use super::{within_range, Direction, Error, ErrorContext, Migration, RegistrationError, Version};
+
+/// The asynchronous counterpart to `Adapter`. Use this trait to connect the `AsyncMigrator` to a
+/// database driver that runs on an async runtime. The futures returned by its methods are `Send`,
+/// so that migrations can be run on a multi-threaded runtime; implementations can still use
+/// `async fn`.
+pub trait AsyncAdapter: Send {
+    /// An alias to a specific trait that extends `Migration`. Typically, the aforementioned trait
+    /// will declare functions that the adapter will use to migrate upwards and downwards. As the
+    /// migrations are borrowed across await points, the type must be `Send` and `Sync`, such as
+    /// `dyn Migration + Send + Sync`.
+    type MigrationType: Migration + Send + Sync + ?Sized;
+
+    /// An adapter-specific error type that can be returned from any of this trait's methods.
+    type Error;
+
+    /// Returns the latest migration version, or `None` if no migrations have been recorded. The
+    /// default calls the deprecated `current_version`, so that adapters written against older
+    /// versions of this crate keep working.
+    fn current_schema_version(&mut self)
+        -> impl Future<Output = Result<Option<Version>, Self::Error>> + Send
+    {
+        #[allow(deprecated)]
+        self.current_version()
+    }
+
+    /// Returns the latest migration version. The default returns the highest of the
+    /// `migrated_versions`.
+    #[deprecated(since = "0.4.0", note = "implement `current_schema_version` instead")]
+    fn current_version(&mut self)
+        -> impl Future<Output = Result<Option<Version>, Self::Error>> + Send
+    {
+        async move { Ok(self.migrated_versions().await?.into_iter().next_back()) }
+    }
+
+    /// Returns a set of the versions of all of the currently applied migrations.
+    fn migrated_versions(&mut self)
+        -> impl Future<Output = Result<BTreeSet<Version>, Self::Error>> + Send;
+
+    /// Applies the specified migration.
+    fn apply_migration(&mut self, migration: &Self::MigrationType)
+        -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Reverts the specified migration.
+    fn revert_migration(&mut self, migration: &Self::MigrationType)
+        -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
+
+/// Maintains an ordered collection of migrations to utilize with an `AsyncAdapter`.
+pub struct AsyncMigrator<T: AsyncAdapter> {
+    adapter: T,
+    migrations: Registry<T::MigrationType>
+}
+
+impl<T: AsyncAdapter> AsyncMigrator<T> {
+    /// Create a migrator with a given adapter.
+    pub fn new(adapter: T) -> AsyncMigrator<T> {
+        AsyncMigrator { adapter, migrations: Registry::new() }
+    }
+
+    /// Get a reference to the adapter.
+    pub fn adapter(&self) -> &T {
+        &self.adapter
+    }
+
+    /// Get a mutable reference to the adapter.
+    pub fn adapter_mut(&mut self) -> &mut T {
+        &mut self.adapter
+    }
+
+    /// Register a migration. If a migration with the same version is already registered, the
+    /// registration fails with `RegistrationError::DuplicateVersion`.
+    pub fn register(&mut self, migration: Box<T::MigrationType>) -> Result<(), RegistrationError> {
+        let version = migration.version();
+        if self.version_registered(version) {
+            Err(RegistrationError::DuplicateVersion(version))
+        } else {
+            self.migrations.insert(version, migration);
+            Ok(())
+        }
+    }
+
+    /// Register a migration. If a migration with the same version is already registered, a warning
+    /// is logged and the registration fails.
+    pub fn register_or_warn(&mut self, migration: Box<T::MigrationType>) {
+        if let Err(err) = self.register(migration) {
+            log_event!(warn, "Migration is already registered", { error:% = err }; "{}", err);
+        }
+    }
+
+    /// Returns true is a migration with the provided version has been registered.
+    pub fn version_registered(&self, version: Version) -> bool {
+        self.migrations.contains_key(&version)
+    }
+
+    /// Returns the set of all registered migration versions.
+    pub fn registered_versions(&self) -> BTreeSet<Version> {
+        self.migrations.keys().cloned().collect()
+    }
+
+    /// Returns the lowest version of the registered migrations, or `None` if no migrations have
+    /// been registered.
+    pub fn first_version(&self) -> Option<Version> {
+        self.migrations.keys().next().copied()
+    }
+
+    /// Returns the highest version of the registered migrations, or `None` if no migrations have
+    /// been registered.
+    pub fn last_version(&self) -> Option<Version> {
+        self.migrations.keys().next_back().copied()
+    }
+
+    /// Returns the latest migration version, or `None` if no migrations have been recorded.
+    pub async fn current_version(&mut self) -> Result<Option<Version>, Error<T::Error>> {
+        self.adapter.current_schema_version().await.map_err(Error::adapter)
+    }
+
+    /// Returns the schema version, which is the latest migration version, or `None` if no
+    /// migrations have been recorded. An alias for `current_version`.
+    pub async fn schema_version(&mut self) -> Result<Option<Version>, Error<T::Error>> {
+        self.current_version().await
+    }
+
+    /// Returns a set of the versions of all of the currently applied migrations.
+    pub async fn migrated_versions(&mut self) -> Result<BTreeSet<Version>, Error<T::Error>> {
+        self.adapter.migrated_versions().await.map_err(Error::adapter)
+    }
+
+    /// Rollback to the specified version (exclusive), or rollback to the state before any
+    /// registered migrations were applied if `None` is specified. Like `Migrator::down`, dependents
+    /// are reverted before their dependencies. Returns the versions of the reverted migrations, in
+    /// the order in which they were reverted.
+    pub async fn down(&mut self, to: Option<Version>) -> Result<Vec<Version>, Error<T::Error>> {
+        let from = self.current_version().await?;
+        if from.is_none() {
+            return Ok(Vec::new());
+        }
+
+        let migrated_versions = self.migrated_versions().await?;
+        // Rollback migrations from latest to oldest, dependents before their dependencies:
+        let targets: Vec<Version> = self.migrations.ordered_versions(None)?.into_iter()
+            .rev()
+            .filter(|&v| within_range(v, to, from) && migrated_versions.contains(&v))
+            .collect();
+
+        for &version in &targets {
+            let migration = &self.migrations[&version];
+            let description = migration.description();
+            log_event!(info, "Reverting migration",
+                       { version = version.get(), description = &*description };
+                       "Reverting migration {:?}: {}", version, description);
+            let result = self.adapter.revert_migration(migration);
+            #[cfg(feature = "tracing")]
+            let result = tracing::Instrument::instrument(result, tracing::info_span!(
+                "migration", version = %version, description = %description,
+                direction = ?Direction::Down));
+            if let Err(err) = result.await {
+                return Err(Error::Adapter {
+                    context: ErrorContext::Migration {
+                        version,
+                        description,
+                        label: migration.label().map(String::from),
+                        direction: Direction::Down,
+                    },
+                    error: err,
+                });
+            }
+        }
+
+        Ok(targets)
+    }
+
+    /// Migrate to the specified version (inclusive). Like `Migrator::up`, migrations are applied in
+    /// ascending order of version, unless their dependencies dictate otherwise. Returns the
+    /// versions of the applied migrations, in the order in which they were applied.
+    pub async fn up(&mut self, to: Option<Version>) -> Result<Vec<Version>, Error<T::Error>> {
+        let mut migrated_versions = self.migrated_versions().await?;
+        // Apply migrations in the same order as `Migrator::up`, dependencies first:
+        let targets: Vec<Version> = self.migrations.ordered_versions(None)?.into_iter()
+            .filter(|&v| within_range(v, None, to) && !migrated_versions.contains(&v))
+            .collect();
+
+        // A dependency beyond the target version must already be applied:
+        for &version in &targets {
+            for dependency in self.migrations[&version].dependencies() {
+                if !migrated_versions.contains(&dependency) {
+                    return Err(Error::MissingDependency(dependency));
+                }
+            }
+            migrated_versions.insert(version);
+        }
+
+        for &version in &targets {
+            let migration = &self.migrations[&version];
+            let description = migration.description();
+            log_event!(info, "Applying migration",
+                       { version = version.get(), description = &*description };
+                       "Applying migration {:?}: {}", version, description);
+            let result = self.adapter.apply_migration(migration);
+            #[cfg(feature = "tracing")]
+            let result = tracing::Instrument::instrument(result, tracing::info_span!(
+                "migration", version = %version, description = %description,
+                direction = ?Direction::Up));
+            if let Err(err) = result.await {
+                return Err(Error::Adapter {
+                    context: ErrorContext::Migration {
+                        version,
+                        description,
+                        label: migration.label().map(String::from),
+                        direction: Direction::Up,
+                    },
+                    error: err,
+                });
+            }
+        }
+
+        Ok(targets)
+    }
+}
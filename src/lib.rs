@@ -1,252 +1,3524 @@
-#[macro_use]
+//! A lightweight database schema migration system.
+//!
+//! Without the default `std` feature, the crate is `no_std` and only requires `alloc`. In that
+//! mode, errors do not implement `std::error::Error`, migration records (which hold a
+//! `SystemTime`) are unavailable, and the durations reported in progress events are zero.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg_attr(not(feature = "tracing"), macro_use)]
 extern crate log;
 
-use std::collections::{BTreeMap, BTreeSet};
-use std::error::Error as StdError;
-use std::fmt::{self, Display, Formatter};
+// Logs at the given level. With the `tracing` feature, the formatted message is emitted as a
+// `tracing` event, within the span of the current migration. Otherwise, with the `kv-logging`
+// feature, the static message is logged together with the given key-value pairs, and without
+// either feature, the formatted message is logged.
+macro_rules! log_event {
+    ($level:ident, $message:expr) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($message);
+        #[cfg(not(feature = "tracing"))]
+        $level!($message);
+    }};
+    ($level:ident, $message:expr, { $($key:ident $(:$capture:tt)? = $value:expr),+ };
+     $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+        #[cfg(all(feature = "kv-logging", not(feature = "tracing")))]
+        $level!($($key $(:$capture)? = $value),+; $message);
+        #[cfg(not(any(feature = "kv-logging", feature = "tracing")))]
+        $level!($($arg)+);
+    }};
+    // Like the above, but also logs the given optional source as a `source` pair when it is set.
+    ($level:ident, $message:expr, source = $source:expr,
+     { $($key:ident $(:$capture:tt)? = $value:expr),+ }; $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+        #[cfg(all(feature = "kv-logging", not(feature = "tracing")))]
+        match $source {
+            Some(source) => $level!(source = source, $($key $(:$capture)? = $value),+; $message),
+            None => $level!($($key $(:$capture)? = $value),+; $message),
+        }
+        #[cfg(not(any(feature = "kv-logging", feature = "tracing")))]
+        $level!($($arg)+);
+    }};
+}
+
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt::{self, Display, Formatter};
+use core::iter::FromIterator;
+use core::ops::{Index, RangeInclusive};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::error::Error as StdError;
+#[cfg(feature = "std")]
+use std::time::SystemTime;
+
+use registry::Registry;
+
+mod registry;
+
+pub mod prelude;
+
+#[cfg(feature = "memory")]
+mod adapters;
+
+#[cfg(feature = "memory")]
+pub use adapters::memory::InMemoryAdapter;
+
+#[cfg(feature = "async")]
+mod asynchronous;
+
+#[cfg(feature = "async")]
+pub use asynchronous::{AsyncAdapter, AsyncMigrator};
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
+/// The integer underlying a `Version`.
+pub type RawVersion = i64;
+
+/// An ordered, unique identifier of a migration. Versions are ordered, formatted and serialized as
+/// their underlying integer.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct Version(pub RawVersion);
+
+impl Version {
+    /// Create a version from its underlying integer.
+    pub const fn new(version: RawVersion) -> Version {
+        Version(version)
+    }
+
+    /// Returns the underlying integer.
+    pub const fn get(self) -> RawVersion {
+        self.0
+    }
+
+    /// Returns the current UTC time as a timestamp-based version of the form `YYYYMMDDHHmmss`,
+    /// such as for the version of a newly generated migration.
+    #[cfg(feature = "std")]
+    pub fn timestamp_now() -> Version {
+        let seconds = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        Version::new(timestamp_from_unix(seconds))
+    }
+
+    /// Returns true if the version is a plausible timestamp of the form `YYYYMMDDHHmmss`. See
+    /// `validate_version_timestamp`.
+    pub fn is_timestamp(self) -> bool {
+        validate_version_timestamp(self)
+    }
+}
+
+impl From<RawVersion> for Version {
+    fn from(version: RawVersion) -> Version {
+        Version(version)
+    }
+}
+
+impl From<Version> for RawVersion {
+    fn from(version: Version) -> RawVersion {
+        version.0
+    }
+}
+
+impl fmt::Debug for Version {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+/// Constructs a timestamp-based version of the form `YYYYMMDDHHmmss` from its components. The
+/// components are not validated; see `validate_version_timestamp`. Being a `const fn`, it may be
+/// used for the version given to `migration!`.
+pub const fn version_from_datetime(year: u32, month: u32, day: u32, hour: u32, minute: u32,
+                                   second: u32) -> RawVersion
+{
+    let date = year as RawVersion * 10000 + month as RawVersion * 100 + day as RawVersion;
+    let time = hour as RawVersion * 10000 + minute as RawVersion * 100 + second as RawVersion;
+    date * 1000000 + time
+}
+
+// Converts seconds since the Unix epoch into a timestamp of the form `YYYYMMDDHHmmss`, using the
+// proleptic Gregorian calendar.
+#[cfg(feature = "std")]
+fn timestamp_from_unix(seconds: u64) -> RawVersion {
+    let (days, time) = ((seconds / 86400) as i64, (seconds % 86400) as u32);
+    // Shift the epoch to 0000-03-01, so that leap days fall at the end of each 400-year era:
+    let days = days + 719468;
+    let (era, day_of_era) = (days / 146097, days % 146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) /
+        365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    version_from_datetime(year as u32, month as u32, day as u32, time / 3600, time / 60 % 60,
+                          time % 60)
+}
+
+/// Returns true if the version is a plausible timestamp of the form `YYYYMMDDHHmmss`, with a year
+/// between 1970 and 2999.
+pub fn validate_version_timestamp(version: Version) -> bool {
+    let version = version.get();
+    let (date, time) = (version / 1000000, version % 1000000);
+    let (year, month, day) = (date / 10000, date / 100 % 100, date % 100);
+    let (hour, minute, second) = (time / 10000, time / 100 % 100, time % 100);
+
+    let leap_year = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap_year => 29,
+        2 => 28,
+        _ => return false,
+    };
+
+    (1970..=2999).contains(&year) && (1..=days_in_month).contains(&day) && hour < 24 &&
+        minute < 60 && second < 60
+}
+
+/// All migrations will implement this trait, and a migration trait specific to the chosen adapter.
+/// This trait defines the metadata for tracking migration sequence and for human reference.
+pub trait Migration {
+    /// An ordered (but not necessarily sequential), unique identifier for this migration.
+    /// Registered migrations will be applied in ascending order by version, unless their
+    /// dependencies dictate otherwise.
+    fn version(&self) -> Version;
+
+    /// A message describing the effects of this migration. The description may be computed at
+    /// runtime, such as from the fields of the migration.
+    fn description(&self) -> String;
+
+    /// The versions of the migrations that must be applied before this migration. Defaults to no
+    /// dependencies.
+    fn dependencies(&self) -> Vec<Version> {
+        Vec::new()
+    }
+
+    /// A fingerprint of this migration's contents, used to detect migrations that were altered
+    /// after being applied. Defaults to `None`, which opts out of verification.
+    fn checksum(&self) -> Option<u64> {
+        None
+    }
+
+    /// The name of the logical module that owns this migration, such as `"auth"` or `"billing"`.
+    /// Defaults to `"default"`.
+    fn namespace(&self) -> &'static str {
+        "default"
+    }
+
+    /// Labels that categorize this migration, such as `"schema"` or `"data"`. Defaults to no tags.
+    fn tags(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// A short, human-readable alias for this migration's version, such as `"create_users_table"`,
+    /// used by error messages and `Migrator::explain_status` in place of opaque version numbers.
+    /// Defaults to `None`.
+    fn label(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Who wrote this migration. Defaults to `None`.
+    fn author(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// The issue or ticket this migration belongs to, such as `"JIRA-123"`. Defaults to `None`.
+    fn ticket(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// The stage of a deployment during which this migration is run. See `Migrator::up_phase`.
+    /// Defaults to `MigrationPhase::Schema`.
+    fn phase(&self) -> MigrationPhase {
+        MigrationPhase::Schema
+    }
+}
+
+/// Shares a migration between several migrators, such as when an application migrates multiple
+/// databases with the same migrations. Registering `Box::new(Arc::clone(&migration))` with each
+/// migrator stores the migration once, as all methods delegate to the shared migration.
+impl<M: Migration + ?Sized> Migration for Arc<M> {
+    fn version(&self) -> Version {
+        (**self).version()
+    }
+
+    fn description(&self) -> String {
+        (**self).description()
+    }
+
+    fn dependencies(&self) -> Vec<Version> {
+        (**self).dependencies()
+    }
+
+    fn checksum(&self) -> Option<u64> {
+        (**self).checksum()
+    }
+
+    fn namespace(&self) -> &'static str {
+        (**self).namespace()
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        (**self).tags()
+    }
+
+    fn label(&self) -> Option<&'static str> {
+        (**self).label()
+    }
+
+    fn author(&self) -> Option<&'static str> {
+        (**self).author()
+    }
+
+    fn ticket(&self) -> Option<&'static str> {
+        (**self).ticket()
+    }
+
+    fn phase(&self) -> MigrationPhase {
+        (**self).phase()
+    }
+}
+
+/// A migration that carries the statements for both of its directions, such as for SQL-based
+/// adapters. Adapters may declare `dyn ReversibleMigration` as their `MigrationType` and execute
+/// the statements directly.
+pub trait ReversibleMigration: Migration {
+    /// The statement that applies this migration.
+    fn up(&self) -> &'static str;
+
+    /// The statement that reverts this migration.
+    fn down(&self) -> &'static str;
+}
+
+/// A migration whose version is known at compile time. `migration!` implements this trait
+/// alongside `Migration`, which allows `register_migrations!` to reject duplicate versions during
+/// compilation.
+pub trait ConstVersion {
+    /// The version of the migration, which must equal the one returned by `Migration::version`.
+    const VERSION: Version;
+}
+
+impl<M: ReversibleMigration + ?Sized> ReversibleMigration for Arc<M> {
+    fn up(&self) -> &'static str {
+        (**self).up()
+    }
+
+    fn down(&self) -> &'static str {
+        (**self).down()
+    }
+}
+
+// `Migration` must remain object safe, so that adapters can declare `dyn Migration` (or a trait
+// extending it) as their `MigrationType`.
+#[allow(dead_code)]
+fn assert_migration_object_safe(_: &dyn Migration) {}
+
+/// A migration's direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    Down,
+    Up,
+}
+
+impl Direction {
+    /// Returns true if this is `Direction::Up`.
+    pub fn is_up(&self) -> bool {
+        *self == Direction::Up
+    }
+
+    /// Returns true if this is `Direction::Down`.
+    pub fn is_down(&self) -> bool {
+        *self == Direction::Down
+    }
+}
+
+/// The stage of a deployment during which a migration is run, such as to apply the schema changes
+/// while deploying and to backfill data asynchronously afterwards.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MigrationPhase {
+    /// A change to the schema, run while deploying.
+    #[default]
+    Schema,
+    /// A change to the data, such as a backfill, which may run after deploying.
+    Data,
+    /// A change that must wait until the deployment is complete, such as dropping a column that
+    /// the previous release still reads.
+    PostDeploy,
+}
+
+/// A migration run to perform with `Migrator::run`, such as one parsed from a command-line
+/// argument or a configuration file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MigrationCommand {
+    /// Applies the pending migrations up to the target version. See `Migrator::up`.
+    Up(Option<Version>),
+    /// Reverts the applied migrations down to the target version. See `Migrator::down`.
+    Down(Option<Version>),
+    /// Applies all pending migrations. See `Migrator::up_to_latest`.
+    UpToLatest,
+    /// Reverts all applied migrations, then applies all registered migrations. See
+    /// `Migrator::reset_and_up`.
+    Reset,
+    /// Applies the given number of pending migrations if positive, or reverts the given number of
+    /// applied migrations if negative. Stops early if there is nothing left to apply or revert.
+    Step(i64),
+}
+
+/// The order in which migrations are applied, as set with `Migrator::set_sort_order`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Higher versions are newer: `up` applies migrations from the lowest version to the highest,
+    /// and `down` reverts them from the highest to the lowest. This is the default.
+    Ascending,
+    /// Lower versions are newer, as in some legacy schemes: `up` applies migrations from the
+    /// highest version to the lowest, and `down` reverts them from the lowest to the highest.
+    Descending,
+}
+
+/// Defines the order of migrations for a migrator created with `Migrator::new_with_comparator`.
+/// Migrations that compare as greater are considered newer: `up` applies migrations from the
+/// least to the greatest, and `down` reverts them from the greatest to the least.
+pub trait VersionComparator: Send + Sync {
+    /// Compares two versions. The ordering must be total, and only equal versions may compare as
+    /// `Ordering::Equal`.
+    fn compare(&self, a: Version, b: Version) -> Ordering;
+}
+
+/// Orders versions numerically, which is the default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NumericComparator;
+
+impl VersionComparator for NumericComparator {
+    fn compare(&self, a: Version, b: Version) -> Ordering {
+        a.cmp(&b)
+    }
+}
+
+impl VersionComparator for SortOrder {
+    fn compare(&self, a: Version, b: Version) -> Ordering {
+        match *self {
+            SortOrder::Ascending => a.cmp(&b),
+            SortOrder::Descending => b.cmp(&a),
+        }
+    }
+}
+
+/// A notification of migration progress, passed to the callback set with
+/// `Migrator::set_progress_callback`.
+#[derive(Clone, Debug)]
+pub enum ProgressEvent {
+    /// A migration is about to be applied.
+    BeforeApply { version: Version, description: String },
+    /// A migration was successfully applied.
+    AfterApply { version: Version, description: String, duration: Duration },
+    /// A migration is about to be reverted.
+    BeforeRevert { version: Version, description: String },
+    /// A migration was successfully reverted.
+    AfterRevert { version: Version, description: String, duration: Duration },
+    /// A migration within the requested range did not need to be executed.
+    MigrationSkipped { version: Version, reason: &'static str },
+}
+
+/// The state of a single migration, as reported by `Migrator::status`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MigrationState {
+    /// The migration is registered and has been applied.
+    Applied,
+    /// The migration is registered but has not been applied.
+    Pending,
+    /// The migration has been applied but is not registered.
+    Orphaned,
+}
+
+/// A single entry of the report returned by `Migrator::status`.
+#[derive(Clone, Debug)]
+pub struct MigrationStatus {
+    /// The version of the migration.
+    pub version: Version,
+    /// The description of the migration, or `"<unregistered>"` for orphaned migrations.
+    pub description: String,
+    /// The state of the migration.
+    pub state: MigrationState,
+}
+
+/// A comparison of the adapter's applied migrations against the registry, as returned by
+/// `Migrator::check_integrity`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntegrityReport {
+    /// The versions of the applied migrations that are not registered.
+    pub orphaned: BTreeSet<Version>,
+    /// The versions of the registered migrations that have not been applied.
+    pub pending: BTreeSet<Version>,
+    /// The versions of the registered migrations that have been applied.
+    pub applied: BTreeSet<Version>,
+}
+
+impl IntegrityReport {
+    /// Returns true if any migration is orphaned or pending.
+    pub fn has_issues(&self) -> bool {
+        !self.orphaned.is_empty() || !self.pending.is_empty()
+    }
+}
+
+/// How a migrator retries migrations that fail, as set with `Migrator::set_retry_policy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of times a migration is attempted, including the first attempt.
+    pub max_attempts: u32,
+    /// How long to wait before each retry.
+    pub backoff: BackoffStrategy,
+}
+
+/// How long a migrator waits before retrying a failed migration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackoffStrategy {
+    /// Waits the same duration before every retry.
+    Fixed(Duration),
+    /// Waits `base` before the first retry, doubling the wait before every further retry, up to
+    /// `max`.
+    Exponential {
+        /// The wait before the first retry.
+        base: Duration,
+        /// The longest wait.
+        max: Duration,
+    },
+}
+
+impl BackoffStrategy {
+    /// Returns the wait before the given retry, starting from `1` for the first retry.
+    pub fn delay(&self, retry: u32) -> Duration {
+        match *self {
+            BackoffStrategy::Fixed(delay) => delay,
+            BackoffStrategy::Exponential { base, max } => {
+                let factor = 1u32.checked_shl(retry.saturating_sub(1)).unwrap_or(u32::MAX);
+                base.checked_mul(factor).map_or(max, |delay| delay.min(max))
+            },
+        }
+    }
+}
+
+/// The net effect that `up` would have, as returned by `Migrator::up_dry_run_diff`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MigrationDiff {
+    /// The versions of the migrations that would be applied, in the order they would be applied.
+    pub to_apply: Vec<Version>,
+    /// The versions of the migrations within the requested range that are already applied.
+    pub already_applied: Vec<Version>,
+    /// The newest applied version once the migrations have been applied, or `None` only if no
+    /// migration would remain applied.
+    pub final_current_version: Option<Version>,
+}
+
+/// Metadata about a migration's execution, as stored by adapters that support it.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MigrationRecord {
+    /// The version of the applied migration.
+    pub version: Version,
+    /// The description of the applied migration.
+    pub description: String,
+    /// The moment at which the migration was applied.
+    pub applied_at: SystemTime,
+    /// How long the migration took to apply.
+    pub duration: Duration,
+    /// Who wrote the migration, if known.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub author: Option<String>,
+    /// The issue or ticket the migration belongs to, if known.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ticket: Option<String>,
+}
+
+/// An attempt to apply or revert a migration, as recorded in `Migrator::event_log`.
+#[cfg(feature = "event-log")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MigrationEvent {
+    /// The moment at which the attempt finished.
+    pub timestamp: SystemTime,
+    /// The version of the migration.
+    pub version: Version,
+    /// The description of the migration.
+    pub description: String,
+    /// The direction in which the migration was run.
+    pub direction: Direction,
+    /// Whether the attempt succeeded.
+    pub success: bool,
+}
+
+/// The set of registered migration versions at a point in time, as taken by `Migrator::snapshot`.
+#[derive(Clone, Debug)]
+pub struct RegistrySnapshot {
+    versions: BTreeSet<Version>,
+}
+
+/// An all-encompassing error type that can be returned during interaction with the migrator
+/// adapter.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Error<E> {
+    /// An error that occurred while interacting with the adapter, along with where it arose.
+    Adapter {
+        /// Whether the error arose during a migration's execution, and if so, which one.
+        context: ErrorContext,
+        /// The underlying error from the adapter.
+        error: E,
+    },
+    /// The requested migration version has not been registered with the migrator.
+    VersionNotFound(Version),
+    /// The dependencies of the registered migrations form a cycle.
+    DependencyCycle,
+    /// A migration depends on a version that is neither registered nor applied.
+    MissingDependency(Version),
+    /// An applied migration's checksum no longer matches the checksum recorded when it was applied.
+    ChecksumMismatch {
+        /// The version of the altered migration.
+        version: Version,
+        /// The checksum recorded by the adapter when the migration was applied.
+        expected: u64,
+        /// The checksum currently reported by the registered migration.
+        actual: u64,
+    },
+    /// The applied migrations are not a contiguous prefix of the registered migrations, as
+    /// reported by `Migrator::verify_order`.
+    OrderViolation {
+        /// The registered migrations that are not applied, although newer migrations are.
+        gaps: Vec<Version>,
+        /// The applied migrations that are not registered.
+        unexpected: Vec<Version>,
+    },
+    /// The adapter's lock could not be acquired, such as when another process is already running
+    /// migrations.
+    LockNotAcquired,
+    /// Migrations were to be reverted, but the adapter does not support reverting migrations (see
+    /// `Adapter::supports_down_migrations`).
+    DownMigrationsUnsupported,
+    /// A migration run took longer than the timeout passed to `Migrator::up_with_timeout`.
+    Timeout {
+        /// The time elapsed when the run was stopped.
+        elapsed: Duration,
+        /// The version of the last migration applied during the run, if any.
+        last_applied: Option<Version>,
+    },
+    /// A migration kept failing until the attempts allowed by the migrator's `RetryPolicy` were
+    /// exhausted.
+    MaxRetriesExceeded {
+        /// The failing migration, as an `ErrorContext::Migration`.
+        context: ErrorContext,
+        /// The number of times the migration was attempted.
+        attempts: u32,
+        /// The error of the last attempt.
+        last_error: E,
+    },
+    /// A migration failed to apply, and rolling back the migrations of the same run also failed.
+    RollbackFailed {
+        /// The error that caused the rollback.
+        apply_error: Box<Error<E>>,
+        /// The error that interrupted the rollback.
+        revert_error: Box<Error<E>>,
+    },
+}
+
+/// Where an adapter error arose, as carried by `Error::Adapter`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ErrorContext {
+    /// The error arose while interacting with the adapter outside of a migration's execution.
+    Adapter,
+    /// The error arose during a migration's execution.
+    Migration {
+        /// The version of the migration that failed.
+        version: Version,
+        /// The description of the migration that failed.
+        description: String,
+        /// The label of the migration that failed, if it has one.
+        #[cfg_attr(feature = "serde", serde(default))]
+        label: Option<String>,
+        /// The direction in which the failed migration was ran.
+        direction: Direction,
+    },
+    /// The error arose while a batch of migrations was handed to the adapter at once (see
+    /// `Adapter::batch_apply`), so the failing migration is unknown.
+    Batch {
+        /// The versions of the migrations in the batch, in the order in which they were ran.
+        versions: Vec<Version>,
+        /// The direction in which the batch was ran.
+        direction: Direction,
+    },
+}
+
+impl<E> Error<E> {
+    /// Wraps an adapter error that arose outside of a migration's execution.
+    pub fn adapter(error: E) -> Error<E> {
+        Error::Adapter { context: ErrorContext::Adapter, error }
+    }
+
+    /// Returns true if this is an `Error::Adapter` that arose outside of a migration's execution.
+    pub fn is_adapter_error(&self) -> bool {
+        matches!(*self, Error::Adapter { context: ErrorContext::Adapter, .. })
+    }
+
+    /// Returns true if this is an `Error::Adapter` or an `Error::MaxRetriesExceeded` that arose
+    /// during a migration's execution.
+    pub fn is_migration_error(&self) -> bool {
+        matches!(self.context(), Some(&ErrorContext::Migration { .. }))
+    }
+
+    /// Returns the version of the failed migration, if this error arose during a migration's
+    /// execution.
+    pub fn migration_version(&self) -> Option<Version> {
+        match self.context() {
+            Some(&ErrorContext::Migration { version, .. }) => Some(version),
+            _ => None,
+        }
+    }
+
+    /// Returns the direction of the failed migration, if this error arose during a migration's
+    /// execution.
+    pub fn migration_direction(&self) -> Option<&Direction> {
+        match self.context() {
+            Some(ErrorContext::Migration { direction, .. }) => Some(direction),
+            _ => None,
+        }
+    }
+
+    /// Returns the adapter error, if this is an `Error::Adapter` or an `Error::MaxRetriesExceeded`.
+    pub fn adapter_error(&self) -> Option<&E> {
+        match *self {
+            Error::Adapter { ref error, .. } => Some(error),
+            Error::MaxRetriesExceeded { ref last_error, .. } => Some(last_error),
+            _ => None,
+        }
+    }
+
+    /// Returns where the adapter error arose, if this is an `Error::Adapter` or an
+    /// `Error::MaxRetriesExceeded`.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match *self {
+            Error::Adapter { ref context, .. } => Some(context),
+            Error::MaxRetriesExceeded { ref context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Converts the adapter error type by applying a function to every contained adapter error,
+    /// including those nested in `Error::RollbackFailed`.
+    pub fn map_adapter_err<F, G>(self, f: G) -> Error<F> where G: Fn(E) -> F {
+        self.map_adapter_err_with(&f)
+    }
+
+    // Implements `map_adapter_err` by reference, so that nested errors can reuse the function.
+    fn map_adapter_err_with<F, G>(self, f: &G) -> Error<F> where G: Fn(E) -> F {
+        match self {
+            Error::Adapter { context, error } => Error::Adapter { context, error: f(error) },
+            Error::VersionNotFound(version) => Error::VersionNotFound(version),
+            Error::DependencyCycle => Error::DependencyCycle,
+            Error::MissingDependency(version) => Error::MissingDependency(version),
+            Error::ChecksumMismatch { version, expected, actual } => {
+                Error::ChecksumMismatch { version, expected, actual }
+            },
+            Error::OrderViolation { gaps, unexpected } => {
+                Error::OrderViolation { gaps, unexpected }
+            },
+            Error::LockNotAcquired => Error::LockNotAcquired,
+            Error::DownMigrationsUnsupported => Error::DownMigrationsUnsupported,
+            Error::Timeout { elapsed, last_applied } => Error::Timeout { elapsed, last_applied },
+            Error::MaxRetriesExceeded { context, attempts, last_error } => {
+                Error::MaxRetriesExceeded { context, attempts, last_error: f(last_error) }
+            },
+            Error::RollbackFailed { apply_error, revert_error } => Error::RollbackFailed {
+                apply_error: Box::new(apply_error.map_adapter_err_with(f)),
+                revert_error: Box::new(revert_error.map_adapter_err_with(f)),
+            },
+        }
+    }
+
+    /// Returns the adapter error, if this is an `Error::Adapter` that arose outside of a
+    /// migration's execution.
+    pub fn into_adapter_error(self) -> Option<E> {
+        match self {
+            Error::Adapter { context: ErrorContext::Adapter, error } => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Returns the version, description, direction and adapter error of the failed migration, if
+    /// this error arose during a migration's execution. For an `Error::MaxRetriesExceeded`, the
+    /// adapter error is that of the last attempt.
+    pub fn into_migration_error(self) -> Option<(Version, String, Direction, E)> {
+        match self {
+            Error::Adapter {
+                context: ErrorContext::Migration { version, description, direction, .. },
+                error,
+            } | Error::MaxRetriesExceeded {
+                context: ErrorContext::Migration { version, description, direction, .. },
+                last_error: error,
+                ..
+            } => Some((version, description, direction, error)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: StdError + 'static> StdError for Error<E> {
+    #[allow(deprecated)]
+    fn description(&self) -> &str {
+        match *self {
+            Error::Adapter { ref error, .. } => error.description(),
+            Error::VersionNotFound(_) => "migration version not found",
+            Error::DependencyCycle => "migration dependency cycle",
+            Error::MissingDependency(_) => "missing migration dependency",
+            Error::ChecksumMismatch { .. } => "migration checksum mismatch",
+            Error::OrderViolation { .. } => "applied migrations out of order",
+            Error::LockNotAcquired => "migration lock not acquired",
+            Error::DownMigrationsUnsupported => "down migrations unsupported",
+            Error::Timeout { .. } => "migration run timed out",
+            Error::MaxRetriesExceeded { ref last_error, .. } => last_error.description(),
+            Error::RollbackFailed { .. } => "migration rollback failed",
+        }
+    }
+
+    #[allow(deprecated)]
+    fn cause(&self) -> Option<&dyn StdError> {
+        self.source()
+    }
+
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            Error::Adapter { ref error, .. } => Some(error),
+            Error::VersionNotFound(_) => None,
+            Error::DependencyCycle => None,
+            Error::MissingDependency(_) => None,
+            Error::ChecksumMismatch { .. } => None,
+            Error::OrderViolation { .. } => None,
+            Error::LockNotAcquired => None,
+            Error::DownMigrationsUnsupported => None,
+            Error::Timeout { .. } => None,
+            Error::MaxRetriesExceeded { ref last_error, .. } => Some(last_error),
+            Error::RollbackFailed { ref revert_error, .. } => Some(&**revert_error),
+        }
+    }
+}
+
+impl<E: Display> Display for Error<E> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Error::Adapter { context: ErrorContext::Adapter, ref error } => {
+                write!(f, "Adapter error: {}", error)
+            },
+            Error::Adapter {
+                context: ErrorContext::Migration { version, ref description, ref label, direction },
+                ref error,
+            } => {
+                let action = if direction.is_up() { "applying" } else { "reverting" };
+                match *label {
+                    Some(ref label) => {
+                        write!(f, "Error {} migration '{}' (v{}), error: {}", action, label,
+                               version, error)
+                    },
+                    None => {
+                        write!(f, "Error {} migration {} ({}), error: {}", action, version,
+                               description, error)
+                    },
+                }
+            },
+            Error::Adapter {
+                context: ErrorContext::Batch { ref versions, direction },
+                ref error,
+            } => {
+                let action = if direction.is_up() { "applying" } else { "reverting" };
+                write!(f, "Error {} batch of migrations [", action)?;
+                for (i, version) in versions.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", version)?;
+                }
+                write!(f, "], error: {}", error)
+            },
+            Error::VersionNotFound(version) => {
+                write!(f, "Migration version {} is not registered", version)
+            },
+            Error::DependencyCycle => write!(f, "Migration dependencies form a cycle"),
+            Error::MissingDependency(version) => {
+                write!(f, "Migration dependency {} is not registered", version)
+            },
+            Error::ChecksumMismatch { version, expected, actual } => {
+                write!(f, "Checksum mismatch for migration {}, expected: {}, actual: {}", version,
+                       expected, actual)
+            },
+            Error::OrderViolation { ref gaps, ref unexpected } => {
+                write!(f, "Applied migrations are out of order, gaps: {:?}, unexpected: {:?}", gaps,
+                       unexpected)
+            },
+            Error::LockNotAcquired => write!(f, "Could not acquire the migration lock"),
+            Error::DownMigrationsUnsupported => {
+                write!(f, "The adapter does not support reverting migrations")
+            },
+            Error::Timeout { elapsed, last_applied } => {
+                write!(f, "Migration run timed out after {:?}, last applied migration: {:?}",
+                       elapsed, last_applied)
+            },
+            Error::MaxRetriesExceeded {
+                context: ErrorContext::Migration { version, .. },
+                attempts,
+                ref last_error,
+            } => {
+                write!(f, "Migration {} failed after {} attempts, last error: {}", version,
+                       attempts, last_error)
+            },
+            Error::MaxRetriesExceeded { attempts, ref last_error, .. } => {
+                write!(f, "Migration failed after {} attempts, last error: {}", attempts,
+                       last_error)
+            },
+            Error::RollbackFailed { ref apply_error, ref revert_error } => {
+                write!(f, "Error rolling back after \"{}\", rollback error: {}", apply_error,
+                       revert_error)
+            },
+        }
+    }
+}
+
+/// Wraps an adapter error in `Error::Adapter` with `ErrorContext::Adapter`, so that the `?`
+/// operator can propagate adapter errors directly. Conversion into `Box<dyn std::error::Error>`
+/// is already provided by the standard library whenever `E` implements `std::error::Error`.
+impl<E> From<E> for Error<E> {
+    fn from(err: E) -> Error<E> {
+        Error::adapter(err)
+    }
+}
+
+/// An error that can be returned when registering a migration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegistrationError {
+    /// A migration with the same version is already registered.
+    DuplicateVersion(Version),
+    /// The migration's version exceeds the highest registered version by more than the maximum
+    /// gap set with `Migrator::set_max_version_gap`.
+    VersionGapExceeded {
+        /// The version of the rejected migration.
+        version: Version,
+        /// The highest registered version at the time of registration.
+        highest: Version,
+        /// The maximum allowed gap.
+        max_gap: RawVersion,
+    },
+    /// A migration was provided under a version that differs from its own.
+    VersionMismatch {
+        /// The version the migration was provided under.
+        key: Version,
+        /// The migration's own version.
+        version: Version,
+    },
+}
+
+#[cfg(feature = "std")]
+impl StdError for RegistrationError {}
+
+impl Display for RegistrationError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            RegistrationError::DuplicateVersion(version) => {
+                write!(f, "Migration with version {} is already registered", version)
+            },
+            RegistrationError::VersionGapExceeded { version, highest, max_gap } => {
+                write!(f, "Migration version {} exceeds the highest registered version {} by more \
+                           than {}", version, highest, max_gap)
+            },
+            RegistrationError::VersionMismatch { key, version } => {
+                write!(f, "Migration with version {} was provided as version {}", version, key)
+            },
+        }
+    }
+}
+
+/// An error that can be returned when squashing migrations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SquashError {
+    /// The start of the squashed range is greater than its end.
+    InvalidRange {
+        /// The start of the range.
+        from: Version,
+        /// The end of the range.
+        to: Version,
+    },
+    /// The replacement's version is already registered outside of the squashed range.
+    VersionConflict(Version),
+}
+
+#[cfg(feature = "std")]
+impl StdError for SquashError {}
+
+impl Display for SquashError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            SquashError::InvalidRange { from, to } => {
+                write!(f, "Invalid squash range, {} is greater than {}", from, to)
+            },
+            SquashError::VersionConflict(version) => {
+                write!(f, "Migration with version {} is registered outside of the squash range",
+                       version)
+            },
+        }
+    }
+}
+
+/// Efficiently implement the `Migration` trait for a given type.
+///
+/// ## Example
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate schemamama;
+/// struct MyMigration;
+/// migration!(MyMigration, 100, "create some lovely database tables");
+///
+/// # fn main() {
+/// use schemamama::Migration;
+/// let m = MyMigration;
+/// assert_eq!(m.version().get(), 100);
+/// assert_eq!(m.description(), "create some lovely database tables");
+/// # }
+/// ```
+///
+/// An optional fourth argument sets the migration's tags:
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate schemamama;
+/// struct BackfillEmails;
+/// migration!(BackfillEmails, 110, "backfill user emails", &["data"]);
+///
+/// # fn main() {
+/// use schemamama::Migration;
+/// assert_eq!(BackfillEmails.tags(), &["data"]);
+/// # }
+/// ```
+///
+/// An optional fifth argument sets the migration's label:
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate schemamama;
+/// struct CreateUsers;
+/// migration!(CreateUsers, 20231015120000, "create users table", &[], "create_users_table");
+///
+/// # fn main() {
+/// use schemamama::Migration;
+/// assert_eq!(CreateUsers.label(), Some("create_users_table"));
+/// # }
+/// ```
+///
+/// Alternatively, any of `tags`, `label`, `author`, `ticket` and `phase` can be given by name:
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate schemamama;
+/// struct AddIndex;
+/// migration!(AddIndex, 120, "add index", author = "alice", ticket = "JIRA-123");
+/// struct BackfillNames;
+/// migration!(BackfillNames, 130, "backfill names", phase = Data);
+///
+/// # fn main() {
+/// use schemamama::{Migration, MigrationPhase};
+/// assert_eq!(AddIndex.author(), Some("alice"));
+/// assert_eq!(AddIndex.ticket(), Some("JIRA-123"));
+/// assert_eq!(AddIndex.label(), None);
+/// assert_eq!(AddIndex.phase(), MigrationPhase::Schema);
+/// assert_eq!(BackfillNames.phase(), MigrationPhase::Data);
+/// # }
+/// ```
+///
+/// The generated implementation is object safe, so migrations of different types can be boxed
+/// together:
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate schemamama;
+/// struct CreateUsers;
+/// migration!(CreateUsers, 100, "create users table");
+/// struct CreatePosts;
+/// migration!(CreatePosts, 110, "create posts table");
+///
+/// # fn main() {
+/// use schemamama::{Migration, Version};
+/// let migrations: Vec<Box<dyn Migration>> = vec![Box::new(CreateUsers), Box::new(CreatePosts)];
+/// let versions: Vec<_> = migrations.iter().map(|m| m.version()).collect();
+/// assert_eq!(versions, vec![Version(100), Version(110)]);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! migration {
+    ($ty:ident, $version:expr, $description:expr) => {
+        impl $crate::Migration for $ty {
+            fn version(&self) -> $crate::Version { <$ty as $crate::ConstVersion>::VERSION }
+            fn description(&self) -> $crate::__private::String { $description.into() }
+        }
+
+        impl $crate::ConstVersion for $ty {
+            const VERSION: $crate::Version = $crate::Version::new($version);
+        }
+    };
+    ($ty:ident, $version:expr, $description:expr, $($key:ident = $value:expr),+ $(,)*) => {
+        impl $crate::Migration for $ty {
+            fn version(&self) -> $crate::Version { <$ty as $crate::ConstVersion>::VERSION }
+            fn description(&self) -> $crate::__private::String { $description.into() }
+            $($crate::migration!(@method $key $value);)+
+        }
+
+        impl $crate::ConstVersion for $ty {
+            const VERSION: $crate::Version = $crate::Version::new($version);
+        }
+    };
+    (@method tags $value:expr) => {
+        fn tags(&self) -> &'static [&'static str] { $value }
+    };
+    (@method label $value:expr) => {
+        fn label(&self) -> ::core::option::Option<&'static str> {
+            ::core::option::Option::Some($value)
+        }
+    };
+    (@method author $value:expr) => {
+        fn author(&self) -> ::core::option::Option<&'static str> {
+            ::core::option::Option::Some($value)
+        }
+    };
+    (@method ticket $value:expr) => {
+        fn ticket(&self) -> ::core::option::Option<&'static str> {
+            ::core::option::Option::Some($value)
+        }
+    };
+    (@method phase $value:expr) => {
+        fn phase(&self) -> $crate::MigrationPhase {
+            // Allows the phase to be given as a bare variant, such as `phase = Data`:
+            #[allow(unused_imports)]
+            use $crate::MigrationPhase::*;
+            $value
+        }
+    };
+    ($ty:ident, $version:expr, $description:expr, $tags:expr) => {
+        impl $crate::Migration for $ty {
+            fn version(&self) -> $crate::Version { <$ty as $crate::ConstVersion>::VERSION }
+            fn description(&self) -> $crate::__private::String { $description.into() }
+            fn tags(&self) -> &'static [&'static str] { $tags }
+        }
+
+        impl $crate::ConstVersion for $ty {
+            const VERSION: $crate::Version = $crate::Version::new($version);
+        }
+    };
+    ($ty:ident, $version:expr, $description:expr, $tags:expr, $label:expr) => {
+        impl $crate::Migration for $ty {
+            fn version(&self) -> $crate::Version { <$ty as $crate::ConstVersion>::VERSION }
+            fn description(&self) -> $crate::__private::String { $description.into() }
+            fn tags(&self) -> &'static [&'static str] { $tags }
+            fn label(&self) -> ::core::option::Option<&'static str> {
+                ::core::option::Option::Some($label)
+            }
+        }
+
+        impl $crate::ConstVersion for $ty {
+            const VERSION: $crate::Version = $crate::Version::new($version);
+        }
+    };
+}
+
+/// Efficiently implement the `Migration` trait for several types at once. Each entry is expanded
+/// with `migration!`.
+///
+/// ## Example
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate schemamama;
+/// struct CreateUsers;
+/// struct AddIndex;
+/// migrations! {
+///     CreateUsers => (10, "create users");
+///     AddIndex => (20, "add index");
+/// }
+///
+/// # fn main() {
+/// use schemamama::Migration;
+/// assert_eq!(CreateUsers.version().get(), 10);
+/// assert_eq!(CreateUsers.description(), "create users");
+/// assert_eq!(AddIndex.version().get(), 20);
+/// assert_eq!(AddIndex.description(), "add index");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! migrations {
+    ($($ty:ident => ($version:expr, $description:expr));* $(;)*) => {
+        $($crate::migration!($ty, $version, $description);)*
+    }
+}
+
+/// Efficiently implement both the `Migration` and `ReversibleMigration` traits for a given type.
+///
+/// ## Example
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate schemamama;
+/// struct CreateUsers;
+/// reversible_migration!(CreateUsers, 100, "create users table",
+///                       "CREATE TABLE users (id BIGINT PRIMARY KEY);",
+///                       "DROP TABLE users;");
+///
+/// # fn main() {
+/// use schemamama::{Migration, ReversibleMigration};
+/// assert_eq!(CreateUsers.version().get(), 100);
+/// assert_eq!(CreateUsers.up(), "CREATE TABLE users (id BIGINT PRIMARY KEY);");
+/// assert_eq!(CreateUsers.down(), "DROP TABLE users;");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! reversible_migration {
+    ($ty:ident, $version:expr, $description:expr, $up:expr, $down:expr) => {
+        $crate::migration!($ty, $version, $description);
+
+        impl $crate::ReversibleMigration for $ty {
+            fn up(&self) -> &'static str { $up }
+            fn down(&self) -> &'static str { $down }
+        }
+    }
+}
+
+/// Register several unit-struct migrations with a migrator at once, evaluating to the
+/// `Result<(), RegistrationError>` of the registrations. Every type must implement `ConstVersion`
+/// (as `migration!` does), and two migrations with the same version are rejected at compile time
+/// rather than by `register` at runtime.
+///
+/// ## Example
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate schemamama;
+/// # use schemamama::{Migrator, Version};
+/// struct CreateUsers;
+/// migration!(CreateUsers, 10, "create users");
+/// struct AddIndex;
+/// migration!(AddIndex, 20, "add index");
+///
+/// # #[cfg(not(feature = "memory"))]
+/// # fn main() {}
+/// # #[cfg(feature = "memory")]
+/// # fn main() {
+/// # use schemamama::InMemoryAdapter;
+/// let mut migrator = Migrator::new(InMemoryAdapter::new());
+/// register_migrations!(migrator, [CreateUsers, AddIndex]).unwrap();
+/// assert_eq!(migrator.last_version(), Some(Version(20)));
+/// # }
+/// ```
+///
+/// Duplicate versions fail to compile:
+///
+/// ```compile_fail
+/// # #[macro_use]
+/// # extern crate schemamama;
+/// # use schemamama::{InMemoryAdapter, Migrator};
+/// struct CreateUsers;
+/// migration!(CreateUsers, 10, "create users");
+/// struct AddIndex;
+/// migration!(AddIndex, 10, "add index");
+///
+/// # fn main() {
+/// let mut migrator = Migrator::new(InMemoryAdapter::new());
+/// register_migrations!(migrator, [CreateUsers, AddIndex]).unwrap();
+/// # }
+/// ```
+#[macro_export]
+macro_rules! register_migrations {
+    ($migrator:expr, [$($ty:ident),* $(,)*]) => {{
+        const _: () = $crate::__private::assert_unique_versions(
+            &[$(<$ty as $crate::ConstVersion>::VERSION),*]);
+        // Evaluate the migrator expression only once:
+        #[allow(unused_variables)]
+        let migrator = &mut $migrator;
+        let result: ::core::result::Result<(), $crate::RegistrationError> = Ok(());
+        $(let result = result.and_then(|()| {
+            migrator.register($crate::__private::Box::new($ty))
+        });)*
+        result
+    }}
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::boxed::Box;
+    pub use alloc::string::String;
+
+    use super::Version;
+
+    // Exposes `within_range` to the property tests in `tests/proptest_within_range.rs`.
+    #[cfg(feature = "proptest")]
+    pub fn within_range(version: Version, low: Option<Version>, high: Option<Version>) -> bool {
+        super::within_range(version, low, high)
+    }
+
+    // Fails compile-time evaluation if any two of the versions are equal.
+    pub const fn assert_unique_versions(versions: &[Version]) {
+        let mut i = 0;
+        while i < versions.len() {
+            let mut j = i + 1;
+            while j < versions.len() {
+                if versions[i].get() == versions[j].get() {
+                    panic!("duplicate migration version");
+                }
+                j += 1;
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Use this trait to connect the migrator to your chosen database technology.
+pub trait Adapter {
+    /// An alias to a specific trait that extends `Migration`. Typically, the aforementioned trait
+    /// will declare functions that the adapter will use to migrate upwards and downwards.
+    /// Adapters that need nothing beyond the metadata of `Migration` can use `dyn Migration`
+    /// directly, which allows migrations of any type to be registered side by side.
+    type MigrationType: Migration + ?Sized;
+
+    /// An adapter-specific error type that can be returned from any of this trait's methods.
+    type Error;
+
+    /// Returns the latest migration version, or `None` if no migrations have been recorded. The
+    /// default calls the deprecated `current_version`, so that adapters written against older
+    /// versions of this crate keep working.
+    fn current_schema_version(&mut self) -> Result<Option<Version>, Self::Error> {
+        #[allow(deprecated)]
+        self.current_version()
+    }
+
+    /// Returns the latest migration version. The default returns the highest of the
+    /// `migrated_versions`.
+    #[deprecated(since = "0.4.0", note = "implement `current_schema_version` instead")]
+    fn current_version(&mut self) -> Result<Option<Version>, Self::Error> {
+        Ok(self.migrated_versions()?.into_iter().next_back())
+    }
+
+    /// Returns a set of the versions of all of the currently applied migrations.
+    fn migrated_versions(&mut self) -> Result<BTreeSet<Version>, Self::Error>;
+
+    /// Returns a set of the versions of the currently applied migrations in the specified
+    /// namespace. Adapters that do not persist namespaces return all applied versions, which is the
+    /// default.
+    fn migrated_versions_in_namespace(&mut self, _namespace: &str)
+        -> Result<BTreeSet<Version>, Self::Error>
+    {
+        self.migrated_versions()
+    }
+
+    /// Returns the versions of all of the currently applied migrations, in the order in which they
+    /// were applied. Adapters that do not record the order of application (such as with a
+    /// timestamp column) return the versions in ascending order, which is the default.
+    fn versions_applied_in_order(&mut self) -> Result<Vec<Version>, Self::Error> {
+        self.migrated_versions().map(|versions| versions.into_iter().collect())
+    }
+
+    /// Applies the specified migration.
+    fn apply_migration(&mut self, migration: &Self::MigrationType) -> Result<(), Self::Error>;
+
+    /// Reverts the specified migration.
+    fn revert_migration(&mut self, migration: &Self::MigrationType) -> Result<(), Self::Error>;
+
+    /// Applies the specified migrations in order, such as in a single round-trip to the database.
+    /// The migrator only calls this if `supports_batch` returns true. Calls `apply_migration` for
+    /// each migration by default. As the migrator cannot tell which migration failed, an error is
+    /// reported with `ErrorContext::Batch`, which lists the versions of the whole batch.
+    fn batch_apply(&mut self, migrations: &[&Self::MigrationType]) -> Result<(), Self::Error> {
+        for migration in migrations {
+            self.apply_migration(migration)?;
+        }
+        Ok(())
+    }
+
+    /// Reverts the specified migrations in order, such as in a single round-trip to the database.
+    /// The migrator only calls this if `supports_batch` returns true. Calls `revert_migration` for
+    /// each migration by default. Like with `batch_apply`, an error is reported with
+    /// `ErrorContext::Batch`.
+    fn batch_revert(&mut self, migrations: &[&Self::MigrationType]) -> Result<(), Self::Error> {
+        for migration in migrations {
+            self.revert_migration(migration)?;
+        }
+        Ok(())
+    }
+
+    /// Records the checksum of a freshly applied migration. Does nothing by default.
+    fn record_checksum(&mut self, _version: Version, _checksum: u64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Returns the checksum recorded for the specified version, or `None` if no checksum has been
+    /// recorded. Returns `None` by default.
+    fn verify_checksum(&mut self, _version: Version) -> Result<Option<u64>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Returns the execution records of the applied migrations. Adapters that do not store such
+    /// metadata return an empty list, which is the default.
+    #[cfg(feature = "std")]
+    fn migration_records(&mut self) -> Result<Vec<MigrationRecord>, Self::Error> {
+        Ok(Vec::new())
+    }
+
+    /// Called by the migrator before a run of migrations, such as to acquire a lock. Does nothing
+    /// by default.
+    fn before_migration_batch(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called by the migrator after a run of migrations, such as to release a lock. `success` is
+    /// `false` if the run was aborted by an error. Does nothing by default.
+    fn after_migration_batch(&mut self, _success: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called by the migrator after each migration that was successfully applied (and committed,
+    /// with transactions), such as to notify an external changelog. Does nothing by default.
+    fn on_migration_applied(&mut self, _version: Version, _description: &str, _duration: Duration)
+        -> Result<(), Self::Error>
+    {
+        Ok(())
+    }
+
+    /// Called by the migrator after each migration that was successfully reverted (and committed,
+    /// with transactions). Does nothing by default.
+    fn on_migration_reverted(&mut self, _version: Version, _description: &str, _duration: Duration)
+        -> Result<(), Self::Error>
+    {
+        Ok(())
+    }
+
+    /// Returns false if the adapter cannot revert migrations, such as for append-only stores, in
+    /// which case the migrator refuses to run any `down` variant with
+    /// `Error::DownMigrationsUnsupported`. Returns true by default.
+    fn supports_down_migrations(&self) -> bool {
+        true
+    }
+
+    /// Returns true if the adapter overrides `batch_apply` and `batch_revert`, in which case the
+    /// migrator hands each run of migrations to them at once, unless a retry policy or rolling back
+    /// on failure requires the migrations to be run one by one. Returns false by default.
+    fn supports_batch(&self) -> bool {
+        false
+    }
+}
+
+/// An extension of `Adapter` for databases that support transactional schema changes. When used
+/// with `Migrator::new_transactional`, each migration is executed within its own transaction. The
+/// default implementations do nothing.
+pub trait TransactionalAdapter: Adapter {
+    /// Begins a transaction.
+    fn begin(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Commits the current transaction.
+    fn commit(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Rolls back the current transaction.
+    fn rollback(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// An extension of `Adapter` for databases that support an exclusive, cross-process lock, such as
+/// an advisory lock. When used with `Migrator::new_locked`, the lock is held for the duration of
+/// every run of migrations.
+pub trait LockableAdapter: Adapter {
+    /// Acquires the lock, failing if it is held by another process.
+    fn acquire_lock(&mut self) -> Result<(), Self::Error>;
+
+    /// Releases the lock.
+    fn release_lock(&mut self) -> Result<(), Self::Error>;
+}
+
+/// An extension of `Adapter` for databases that can record a migration as applied without
+/// executing it, as required by `Migrator::fake_up`.
+pub trait MarkableAdapter: Adapter {
+    /// Records the migration of the specified version as applied without executing it.
+    fn mark_applied(&mut self, version: Version) -> Result<(), Self::Error>;
+}
+
+/// Maintains an ordered collection of migrations to utilize.
+pub struct Migrator<T: Adapter> {
+    adapter: T,
+    migrations: Registry<T::MigrationType>,
+    rollback_on_failure: bool,
+    comparator: Option<Arc<dyn VersionComparator>>,
+    source: Option<String>,
+    locks: Option<Locks<T>>,
+    lock_held: bool,
+    max_version_gap: Option<RawVersion>,
+    retry_policy: Option<RetryPolicy>,
+    #[cfg(feature = "event-log")]
+    event_log: Vec<MigrationEvent>,
+    progress_callback: Option<Arc<dyn Fn(ProgressEvent) + Send + Sync>>,
+    transactions: Option<Transactions<T>>,
+}
+
+// The transaction controls of a `TransactionalAdapter`, captured by `Migrator::new_transactional`.
+struct Transactions<T: Adapter> {
+    begin: fn(&mut T) -> Result<(), T::Error>,
+    commit: fn(&mut T) -> Result<(), T::Error>,
+    rollback: fn(&mut T) -> Result<(), T::Error>,
+}
+
+impl<T: Adapter> Clone for Transactions<T> {
+    fn clone(&self) -> Transactions<T> {
+        Transactions { begin: self.begin, commit: self.commit, rollback: self.rollback }
+    }
+}
+
+// The lock controls of a `LockableAdapter`, captured by `Migrator::new_locked`.
+struct Locks<T: Adapter> {
+    acquire: fn(&mut T) -> Result<(), T::Error>,
+    release: fn(&mut T) -> Result<(), T::Error>,
+}
+
+impl<T: Adapter> Clone for Locks<T> {
+    fn clone(&self) -> Locks<T> {
+        Locks { acquire: self.acquire, release: self.release }
+    }
+}
+
+// Holds the adapter's lock for the lifetime of a locked run, releasing it when dropped (even if
+// the run panics).
+struct LockGuard<'a, T: Adapter> {
+    migrator: &'a mut Migrator<T>,
+    release: fn(&mut T) -> Result<(), T::Error>,
+}
+
+impl<'a, T: Adapter> Drop for LockGuard<'a, T> {
+    fn drop(&mut self) {
+        self.migrator.lock_held = false;
+        if (self.release)(&mut self.migrator.adapter).is_err() {
+            log_event!(warn, "Failed to release the migration lock");
+        }
+    }
+}
+
+// The outcome of planning a migration run.
+struct Plan {
+    // The versions of the migrations to execute, in execution order.
+    targets: Vec<Version>,
+    // The versions within the requested range that need no execution.
+    skipped: Vec<Version>,
+}
+
+impl<T: Adapter> Migrator<T> {
+    /// Create a migrator with a given adapter.
+    pub fn new(adapter: T) -> Migrator<T> {
+        Migrator {
+            adapter,
+            migrations: Registry::new(),
+            rollback_on_failure: false,
+            comparator: None,
+            source: None,
+            locks: None,
+            lock_held: false,
+            max_version_gap: None,
+            retry_policy: None,
+            #[cfg(feature = "event-log")]
+            event_log: Vec::new(),
+            progress_callback: None,
+            transactions: None,
+        }
+    }
+
+    /// Create a migrator with a given adapter, which orders migrations with the given comparator
+    /// rather than numerically by version.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate schemamama;
+    /// use std::cmp::Ordering;
+    /// use schemamama::{Migrator, Version, VersionComparator};
+    ///
+    /// // Orders versions of the form `MMmm` (major, minor) by minor number first.
+    /// struct MinorFirst;
+    ///
+    /// impl VersionComparator for MinorFirst {
+    ///     fn compare(&self, a: Version, b: Version) -> Ordering {
+    ///         (a.0 % 100, a.0 / 100).cmp(&(b.0 % 100, b.0 / 100))
+    ///     }
+    /// }
+    ///
+    /// struct First;
+    /// migration!(First, 201, "first");
+    /// struct Second;
+    /// migration!(Second, 102, "second");
+    ///
+    /// # #[cfg(not(feature = "memory"))]
+    /// # fn main() {}
+    /// # #[cfg(feature = "memory")]
+    /// # fn main() {
+    /// # use schemamama::InMemoryAdapter;
+    /// let mut migrator = Migrator::new_with_comparator(InMemoryAdapter::new(), MinorFirst);
+    /// migrator.register(Box::new(Second)).unwrap();
+    /// migrator.register(Box::new(First)).unwrap();
+    /// migrator.up(Some(Version(201))).unwrap();
+    /// assert_eq!(migrator.migrated_versions().unwrap(), [Version(201)].iter().cloned().collect());
+    /// # }
+    /// ```
+    pub fn new_with_comparator<C>(adapter: T, comparator: C) -> Migrator<T>
+        where C: VersionComparator + 'static
+    {
+        let mut migrator = Migrator::new(adapter);
+        migrator.comparator = Some(Arc::new(comparator));
+        migrator
+    }
+
+    /// Create a migrator with a given transactional adapter. Each migration will be executed within
+    /// its own transaction, which is rolled back if the migration fails.
+    pub fn new_transactional(adapter: T) -> Migrator<T> where T: TransactionalAdapter {
+        let mut migrator = Migrator::new(adapter);
+        migrator.transactions = Some(Transactions {
+            begin: T::begin,
+            commit: T::commit,
+            rollback: T::rollback,
+        });
+        migrator
+    }
+
+    /// Create a migrator with a given lockable adapter. The adapter's lock is held for the duration
+    /// of every run of migrations (such as `up` or `down`), so that concurrent processes sharing
+    /// the database do not migrate it simultaneously. A run fails with `Error::LockNotAcquired` if
+    /// the lock cannot be acquired.
+    pub fn new_locked(adapter: T) -> Migrator<T> where T: LockableAdapter {
+        let mut migrator = Migrator::new(adapter);
+        migrator.locks = Some(Locks { acquire: T::acquire_lock, release: T::release_lock });
+        migrator
+    }
+
+    /// Create a migrator with a given adapter and map of migrations keyed by version. Fails with
+    /// `RegistrationError::VersionMismatch` if a key differs from its migration's version.
+    pub fn from_migrations(adapter: T, migrations: BTreeMap<Version, Box<T::MigrationType>>)
+        -> Result<Migrator<T>, RegistrationError>
+    {
+        let mut migrator = Migrator::new(adapter);
+        for (key, migration) in migrations {
+            let version = migration.version();
+            if key != version {
+                return Err(RegistrationError::VersionMismatch { key, version });
+            }
+            migrator.migrations.insert(version, migration);
+        }
+        Ok(migrator)
+    }
+
+    /// Create a migrator with a given adapter and migrations. Migrations that fail to register,
+    /// such as those with duplicate versions, are skipped with a warning, like with
+    /// `register_or_warn`.
+    pub fn with_adapter_and_iter<I>(adapter: T, migrations: I) -> Migrator<T>
+        where I: IntoIterator<Item = Box<T::MigrationType>>
+    {
+        let mut migrator = Migrator::new(adapter);
+        migrator.extend(migrations);
+        migrator
+    }
+
+    /// Sets a callback that receives progress events as migrations are executed. The callback is
+    /// called synchronously, on the thread running the migrations.
+    pub fn set_progress_callback<F>(&mut self, callback: F) -> &mut Migrator<T>
+        where F: Fn(ProgressEvent) + Send + Sync + 'static
+    {
+        self.progress_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sets whether a migration run should be rolled back when a migration fails to apply. If
+    /// enabled, the failing migration and every migration applied earlier in the same run are
+    /// reverted in reverse order. Disabled by default.
+    pub fn set_rollback_on_failure(&mut self, rollback_on_failure: bool) -> &mut Migrator<T> {
+        self.rollback_on_failure = rollback_on_failure;
+        self
+    }
+
+    /// Sets how migrations that fail to apply or revert are retried, such as for adapters that
+    /// connect to a remote database. Once the policy's attempts are exhausted, the run fails with
+    /// `Error::MaxRetriesExceeded`. With transactions, each failed attempt is rolled back before
+    /// the next one begins. Without `std` there is no clock to wait on, so retries are immediate.
+    /// Failed migrations are not retried by default.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) -> &mut Migrator<T> {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Sets the order in which migrations are applied. With `SortOrder::Descending`, every
+    /// directional notion of a run is reversed: `up` applies the highest versions first and
+    /// migrates "up" towards lower versions, `down` reverts the lowest versions first, runs start
+    /// from the lowest applied version, and the range bounds of `up`, `down` and `migrate_to` are
+    /// mirrored accordingly. `current_version` is unaffected and still returns the adapter's
+    /// current version, which is the highest applied version. Defaults to `SortOrder::Ascending`.
+    /// Replaces any comparator given to `new_with_comparator`.
+    pub fn set_sort_order(&mut self, sort_order: SortOrder) -> &mut Migrator<T> {
+        self.comparator = match sort_order {
+            SortOrder::Ascending => None,
+            SortOrder::Descending => Some(Arc::new(SortOrder::Descending)),
+        };
+        self
+    }
+
+    /// Sets the maximum amount by which a newly registered migration's version may exceed the
+    /// highest registered version, guarding against accidentally skipping versions (such as with a
+    /// mistyped timestamp). `None` disables the check, which is the default.
+    pub fn set_max_version_gap(&mut self, max_gap: Option<RawVersion>) -> &mut Migrator<T> {
+        self.max_version_gap = max_gap;
+        self
+    }
+
+    /// Get a reference to the adapter.
+    pub fn adapter(&self) -> &T {
+        &self.adapter
+    }
+
+    /// Get a mutable reference to the adapter.
+    pub fn adapter_mut(&mut self) -> &mut T {
+        &mut self.adapter
+    }
+
+    /// Consumes the migrator, returning the adapter. Use `take_adapter` to keep the migrator.
+    pub fn into_adapter(self) -> T {
+        self.adapter
+    }
+
+    /// Returns the adapter, leaving a default adapter in its place. The registered migrations and
+    /// settings are kept, so the migrator can still be inspected.
+    pub fn take_adapter(&mut self) -> T where T: Default {
+        core::mem::take(&mut self.adapter)
+    }
+
+    /// Register a migration. If a migration with the same version is already registered, the
+    /// registration fails with `RegistrationError::DuplicateVersion`. If a maximum version gap is
+    /// set and exceeded, the registration fails with `RegistrationError::VersionGapExceeded`.
+    pub fn register(&mut self, migration: Box<T::MigrationType>) -> Result<(), RegistrationError> {
+        let version = migration.version();
+        self.check_registration(version, self.last_version())?;
+        self.migrations.insert(version, migration);
+        Ok(())
+    }
+
+    /// Registers all of the migrations, in order, or none of them. If any migration would fail to
+    /// register with `register`, including because of a duplicate version within the batch, the
+    /// registry is left untouched and the error is returned together with all of the migrations.
+    #[allow(clippy::type_complexity)]
+    pub fn register_many(&mut self, migrations: Vec<Box<T::MigrationType>>)
+        -> Result<(), (RegistrationError, Vec<Box<T::MigrationType>>)>
+    {
+        let mut batch = BTreeSet::new();
+        let mut highest = self.last_version();
+        for migration in &migrations {
+            let version = migration.version();
+            let checked = if batch.insert(version) {
+                self.check_registration(version, highest)
+            } else {
+                Err(RegistrationError::DuplicateVersion(version))
+            };
+            if let Err(err) = checked {
+                return Err((err, migrations));
+            }
+            highest = highest.max(Some(version));
+        }
+
+        for migration in migrations {
+            self.migrations.insert(migration.version(), migration);
+        }
+        Ok(())
+    }
+
+    // Checks whether a migration with the given version may be registered, given the highest
+    // version registered so far.
+    fn check_registration(&self, version: Version, highest: Option<Version>)
+        -> Result<(), RegistrationError>
+    {
+        if self.version_registered(version) {
+            return Err(RegistrationError::DuplicateVersion(version));
+        }
+
+        if let (Some(max_gap), Some(highest)) = (self.max_version_gap, highest) {
+            // A gap too large to represent certainly exceeds the maximum:
+            let exceeded = match version.get().checked_sub(highest.get()) {
+                Some(gap) => gap > max_gap,
+                None => true,
+            };
+            if version > highest && exceeded {
+                return Err(RegistrationError::VersionGapExceeded { version, highest, max_gap });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Register a migration. If the registration fails, such as when a migration with the same
+    /// version is already registered, a warning is logged instead.
+    pub fn register_or_warn(&mut self, migration: Box<T::MigrationType>) {
+        if let Err(err) = self.register(migration) {
+            log_event!(warn, "Failed to register migration", { error:% = err }; "{}", err);
+        }
+    }
+
+    /// Registers the migration and returns the migrator, allowing for fluent construction. Fails
+    /// with `RegistrationError::DuplicateVersion` like `register`.
+    pub fn with_migration(mut self, migration: Box<T::MigrationType>)
+        -> Result<Migrator<T>, RegistrationError>
+    {
+        self.register(migration)?;
+        Ok(self)
+    }
+
+    /// Tags the migrator with the source of its migrations, such as the path of the file that
+    /// defines them. The source prefixes the migrator's log messages, which distinguishes between
+    /// multiple migrators.
+    pub fn loaded_from<S: Into<String>>(mut self, source: S) -> Migrator<T> {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Returns the source given to `loaded_from`, if any.
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// Registers all of the migrations and returns the migrator. Fails on the first migration with
+    /// an already registered version.
+    pub fn with_migrations<I>(mut self, migrations: I) -> Result<Migrator<T>, RegistrationError>
+        where I: IntoIterator<Item = Box<T::MigrationType>>
+    {
+        for migration in migrations {
+            self.register(migration)?;
+        }
+        Ok(self)
+    }
+
+    /// Removes a registered migration from the registry and returns it, or `None` if no migration
+    /// with the provided version has been registered. The adapter is not affected.
+    pub fn deregister(&mut self, version: Version) -> Option<Box<T::MigrationType>> {
+        self.migrations.remove(&version)
+    }
+
+    /// Registers a migration in place of the registered migration with the same version, logging
+    /// a warning, and returns the replaced migration. Unlike `register`, no checks are made; if no
+    /// migration with the same version has been registered, the migration is simply registered and
+    /// `None` is returned. The adapter is not affected.
+    pub fn replace(&mut self, migration: Box<T::MigrationType>) -> Option<Box<T::MigrationType>> {
+        let version = migration.version();
+        let replaced = self.migrations.insert(version, migration);
+        if replaced.is_some() {
+            log_event!(warn, "Migration replaced",
+                       source = self.source(), { version = version.get() };
+                       "{}Migration {} replaced", self.log_prefix(), version);
+        }
+        replaced
+    }
+
+    /// Replaces the registered migrations with versions in `[from, to]` by the single replacement
+    /// migration. This only affects the registry; updating the adapter's record of applied
+    /// migrations is up to the caller.
+    pub fn squash(&mut self, from: Version, to: Version, replacement: Box<T::MigrationType>)
+        -> Result<(), SquashError>
+    {
+        if from > to {
+            return Err(SquashError::InvalidRange { from, to });
+        }
+
+        let version = replacement.version();
+        if !(from..=to).contains(&version) && self.version_registered(version) {
+            return Err(SquashError::VersionConflict(version));
+        }
+
+        let squashed: Vec<Version> = self.migrations.range(from..=to).map(|(&v, _)| v).collect();
+        for v in squashed {
+            self.migrations.remove(&v);
+        }
+        self.migrations.insert(version, replacement);
+        Ok(())
+    }
+
+    /// Returns true is a migration with the provided version has been registered.
+    pub fn version_registered(&self, version: Version) -> bool {
+        self.migrations.contains_key(&version)
+    }
+
+    /// Returns the registered migration with the provided version, or `None` if no such migration
+    /// has been registered.
+    pub fn find_migration(&self, version: Version) -> Option<&T::MigrationType> {
+        self.migrations.get(&version)
+    }
+
+    /// Returns a mutable reference to the registered migration with the provided version, or
+    /// `None` if no such migration has been registered.
+    pub fn find_migration_mut(&mut self, version: Version) -> Option<&mut T::MigrationType> {
+        self.migrations.get_mut(&version)
+    }
+
+    /// Returns the registered migration at the provided position in ascending order of version
+    /// (`0` being the migration with the lowest version), or `None` if fewer migrations have been
+    /// registered.
+    pub fn migration_at(&self, position: usize) -> Option<&T::MigrationType> {
+        self.migrations.iter().nth(position).map(|(_, migration)| migration)
+    }
+
+    /// Iterates over the registered migrations in ascending order of version.
+    pub fn iter(&self) -> Iter<'_, T::MigrationType> {
+        Iter { inner: self.migrations.iter() }
+    }
+
+    /// Iterates mutably over the registered migrations in ascending order of version. Mutating a
+    /// migration such that its `version` changes leaves the registry inconsistent, and must be
+    /// avoided.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T::MigrationType> {
+        IterMut { inner: self.migrations.iter_mut() }
+    }
+
+    /// Returns the set of all registered migration versions.
+    pub fn registered_versions(&self) -> BTreeSet<Version> {
+        self.migrations.keys().cloned().collect()
+    }
+
+    /// Returns the registered migration versions within `[low, high]`. The set is empty if `low` is
+    /// greater than `high`.
+    pub fn versions_between(&self, low: Version, high: Version) -> BTreeSet<Version> {
+        if low > high {
+            return BTreeSet::new();
+        }
+        self.migrations.range(low..=high).map(|(&v, _)| v).collect()
+    }
+
+    /// Returns the number of registered migrations.
+    pub fn migration_count(&self) -> usize {
+        self.migrations.len()
+    }
+
+    /// Returns the number of registered migrations with a version greater than the given version.
+    pub fn count_versions_after(&self, version: Version) -> usize {
+        self.migrations.keys().filter(|&&v| v > version).count()
+    }
+
+    /// Returns the number of registered migrations with a version less than the given version.
+    pub fn count_versions_before(&self, version: Version) -> usize {
+        self.migrations.keys().filter(|&&v| v < version).count()
+    }
+
+    /// Returns true if no migrations have been registered.
+    pub fn is_empty(&self) -> bool {
+        self.migrations.is_empty()
+    }
+
+    /// Records which migration versions are currently registered, to later `restore` the registry.
+    pub fn snapshot(&self) -> RegistrySnapshot {
+        RegistrySnapshot { versions: self.registered_versions() }
+    }
+
+    /// Deregisters every migration that was registered after the snapshot was taken. Migrations
+    /// that have since been deregistered are not registered again. The adapter is not affected.
+    pub fn restore(&mut self, snapshot: RegistrySnapshot) {
+        self.migrations.retain(|version, _| snapshot.versions.contains(version));
+    }
+
+    /// Returns the lowest version of the registered migrations, or `None` if no migrations have
+    /// been registered.
+    pub fn first_version(&self) -> Option<Version> {
+        self.migrations.keys().next().copied()
+    }
+
+    /// Returns the highest version of the registered migrations, or `None` if no migrations have
+    /// been registered.
+    pub fn last_version(&self) -> Option<Version> {
+        self.migrations.keys().next_back().copied()
+    }
+
+    /// Checks that every dependency declared by the registered migrations is itself registered, and
+    /// that the dependencies do not form a cycle.
+    pub fn validate(&self) -> Result<(), Error<T::Error>> {
+        self.ordered_versions().map(|_| ())
+    }
+
+    /// Returns the registered versions that are not plausible `YYYYMMDDHHmmss` timestamps, as
+    /// determined by `validate_version_timestamp`.
+    pub fn validate_registered_versions(&self) -> Vec<Version> {
+        self.migrations.keys().cloned().filter(|&v| !validate_version_timestamp(v)).collect()
+    }
+
+    // Returns the registered versions sorted topologically by their dependencies. Among the
+    // migrations whose dependencies are satisfied, the lowest version always comes first, so the
+    // result is in ascending order when no dependencies are declared.
+    fn ordered_versions(&self) -> Result<Vec<Version>, Error<T::Error>> {
+        self.migrations.ordered_versions(self.comparator.as_deref())
+    }
+
+    /// Returns the latest migration version, or `None` if no migrations have been recorded.
+    pub fn current_version(&mut self) -> Result<Option<Version>, Error<T::Error>> {
+        match self.adapter.current_schema_version() {
+            Ok(ver) => Ok(ver),
+            Err(err) => Err(Error::adapter(err)),
+        }
+    }
+
+    /// Returns the schema version, which is the latest migration version, or `None` if no
+    /// migrations have been recorded. An alias for `current_version`.
+    pub fn schema_version(&mut self) -> Result<Option<Version>, Error<T::Error>> {
+        self.current_version()
+    }
+
+    // Returns the newest applied version according to the migrator's ordering. With the default
+    // numeric ordering, this is the adapter's current version.
+    fn newest_version(&mut self) -> Result<Option<Version>, Error<T::Error>> {
+        match self.comparator {
+            Some(_) => {
+                let migrated_versions = self.migrated_versions()?;
+                Ok(self.newest_of(migrated_versions))
+            },
+            None => self.current_version(),
+        }
+    }
+
+    // Compares two versions according to the migrator's ordering, in which newer migrations are
+    // greater.
+    fn compare(&self, a: Version, b: Version) -> Ordering {
+        match self.comparator {
+            Some(ref comparator) => comparator.compare(a, b),
+            None => a.cmp(&b),
+        }
+    }
+
+    // Returns the newest of the versions according to the migrator's ordering.
+    fn newest_of<I>(&self, versions: I) -> Option<Version> where I: IntoIterator<Item = Version> {
+        versions.into_iter().max_by(|a, b| self.compare(*a, *b))
+    }
+
+    // Like `within_range`, but according to the migrator's ordering.
+    fn within(&self, version: Version, low: Option<Version>, high: Option<Version>) -> bool {
+        match self.comparator {
+            Some(_) => {
+                low.is_none_or(|low| self.compare(version, low) == Ordering::Greater) &&
+                    high.is_none_or(|high| self.compare(version, high) != Ordering::Greater)
+            },
+            None => within_range(version, low, high),
+        }
+    }
+
+    /// Returns a set of the versions of all of the currently applied migrations.
+    pub fn migrated_versions(&mut self) -> Result<BTreeSet<Version>, Error<T::Error>> {
+        match self.adapter.migrated_versions() {
+            Ok(vers) => Ok(vers),
+            Err(err) => Err(Error::adapter(err)),
+        }
+    }
+
+    /// Returns the versions of all of the currently applied migrations, in the order in which they
+    /// were applied if the adapter records it, or otherwise in ascending order. See
+    /// `Adapter::versions_applied_in_order`.
+    pub fn versions_applied_in_order(&mut self) -> Result<Vec<Version>, Error<T::Error>> {
+        self.adapter.versions_applied_in_order().map_err(Error::adapter)
+    }
+
+    /// Returns the set of registered migration versions that have been applied.
+    pub fn applied_and_registered(&mut self) -> Result<BTreeSet<Version>, Error<T::Error>> {
+        let migrated_versions = self.migrated_versions()?;
+        Ok(self.migrations.keys().filter(|v| migrated_versions.contains(v)).cloned().collect())
+    }
+
+    /// Returns the set of applied migration versions that are not registered, such as those of
+    /// deleted migrations.
+    pub fn applied_but_not_registered(&mut self) -> Result<BTreeSet<Version>, Error<T::Error>> {
+        let migrated_versions = self.migrated_versions()?;
+        Ok(migrated_versions.into_iter().filter(|v| !self.version_registered(*v)).collect())
+    }
+
+    /// Returns the set of registered migration versions that have not yet been applied.
+    pub fn registered_but_not_applied(&mut self) -> Result<BTreeSet<Version>, Error<T::Error>> {
+        let migrated_versions = self.migrated_versions()?;
+        Ok(self.migrations.keys().filter(|v| !migrated_versions.contains(v)).cloned().collect())
+    }
+
+    /// Returns the set of registered migration versions that have not yet been applied. Equivalent
+    /// to `registered_but_not_applied`.
+    pub fn pending_versions(&mut self) -> Result<BTreeSet<Version>, Error<T::Error>> {
+        self.registered_but_not_applied()
+    }
+
+    /// Returns the set of registered migration versions in the given phase that have not yet been
+    /// applied.
+    pub fn pending_in_phase(&mut self, phase: MigrationPhase)
+        -> Result<BTreeSet<Version>, Error<T::Error>>
+    {
+        let migrated_versions = self.migrated_versions()?;
+        Ok(self.migrations.iter()
+            .filter(|(v, migration)| !migrated_versions.contains(v) && migration.phase() == phase)
+            .map(|(&v, _)| v)
+            .collect())
+    }
+
+    /// Returns the set of registered migration versions that have been applied. Unlike
+    /// `migrated_versions`, applied versions that are not registered are excluded. Equivalent to
+    /// `applied_and_registered`.
+    pub fn applied_versions(&mut self) -> Result<BTreeSet<Version>, Error<T::Error>> {
+        self.applied_and_registered()
+    }
+
+    /// Returns the number of currently applied migrations.
+    pub fn applied_count(&mut self) -> Result<usize, Error<T::Error>> {
+        Ok(self.migrated_versions()?.len())
+    }
+
+    /// Returns the number of registered migrations that have not yet been applied.
+    pub fn pending_count(&mut self) -> Result<usize, Error<T::Error>> {
+        self.count_pending()
+    }
+
+    /// Counts the registered migrations that have not yet been applied, without collecting their
+    /// versions.
+    pub fn count_pending(&mut self) -> Result<usize, Error<T::Error>> {
+        let migrated_versions = self.migrated_versions()?;
+        Ok(self.migrations.keys().filter(|v| !migrated_versions.contains(v)).count())
+    }
+
+    /// Counts the registered migrations that have been applied, without collecting their versions.
+    /// Unlike `applied_count`, applied versions that are not registered are excluded.
+    pub fn count_applied(&mut self) -> Result<usize, Error<T::Error>> {
+        let migrated_versions = self.migrated_versions()?;
+        Ok(self.migrations.keys().filter(|v| migrated_versions.contains(v)).count())
+    }
+
+    /// Returns the set of applied migration versions that are not registered, such as those of
+    /// deleted migrations. Equivalent to `applied_but_not_registered`.
+    pub fn orphaned_versions(&mut self) -> Result<BTreeSet<Version>, Error<T::Error>> {
+        self.applied_but_not_registered()
+    }
+
+    /// Returns the state of every registered or applied migration, sorted by version in ascending
+    /// order.
+    pub fn status(&mut self) -> Result<Vec<MigrationStatus>, Error<T::Error>> {
+        let applied = self.applied_and_registered()?;
+        let orphaned = self.applied_but_not_registered()?;
+        let pending = self.registered_but_not_applied()?;
+
+        let mut report: Vec<MigrationStatus> = applied.into_iter()
+            .map(|version| (version, MigrationState::Applied))
+            .chain(pending.into_iter().map(|version| (version, MigrationState::Pending)))
+            .map(|(version, state)| MigrationStatus {
+                version,
+                description: self.migrations[&version].description(),
+                state,
+            })
+            .chain(orphaned.into_iter().map(|version| MigrationStatus {
+                version,
+                description: "<unregistered>".into(),
+                state: MigrationState::Orphaned,
+            }))
+            .collect();
+        report.sort_by_key(|status| status.version);
+
+        Ok(report)
+    }
+
+    /// Compares the adapter's applied migrations against the registered migrations. Nothing is
+    /// modified.
+    pub fn check_integrity(&mut self) -> Result<IntegrityReport, Error<T::Error>> {
+        let migrated_versions = self.migrated_versions()?;
+        let (applied, pending) =
+            self.migrations.keys().partition(|v| migrated_versions.contains(v));
+        let orphaned =
+            migrated_versions.into_iter().filter(|v| !self.version_registered(*v)).collect();
+        Ok(IntegrityReport { orphaned, pending, applied })
+    }
+
+    /// Returns every attempt to apply or revert a migration made by this migrator, in order,
+    /// including failed attempts and retries.
+    #[cfg(feature = "event-log")]
+    pub fn event_log(&self) -> &[MigrationEvent] {
+        &self.event_log
+    }
+
+    /// Returns the execution records of the applied migrations, as stored by the adapter.
+    #[cfg(feature = "std")]
+    pub fn history(&mut self) -> Result<Vec<MigrationRecord>, Error<T::Error>> {
+        self.adapter.migration_records().map_err(Error::adapter)
+    }
+
+    /// Returns the migrations that `down` would revert for the specified version, in the order that
+    /// they would be reverted. No migrations are executed.
+    pub fn plan_down(&mut self, to: Option<Version>)
+        -> Result<Vec<&T::MigrationType>, Error<T::Error>>
+    {
+        let from = self.newest_version()?;
+        let migrated_versions = self.migrated_versions()?;
+        let plan = self.plan_down_where(to, from, &migrated_versions, |_| true)?;
+        let migrations = &self.migrations;
+        Ok(plan.targets.iter().map(|v| &migrations[v]).collect())
+    }
+
+    /// Returns the migrations that `up` would apply for the specified version, in the order that
+    /// they would be applied. No migrations are executed. Returns `Error::MissingDependency` if a
+    /// migration would be applied before one of its dependencies.
+    pub fn plan_up(&mut self, to: Option<Version>)
+        -> Result<Vec<&T::MigrationType>, Error<T::Error>>
+    {
+        let migrated_versions = self.migrated_versions()?;
+        let plan = self.plan_up_where(to, migrated_versions, |_| true)?;
+        let migrations = &self.migrations;
+        Ok(plan.targets.iter().map(|v| &migrations[v]).collect())
+    }
+
+    /// Computes the net effect of `up` for the specified version: the migrations that would be
+    /// applied, those that are already applied, and the resulting current version. No migrations
+    /// are executed.
+    pub fn up_dry_run_diff(&mut self, to: Option<Version>)
+        -> Result<MigrationDiff, Error<T::Error>>
+    {
+        let mut migrated_versions = self.migrated_versions()?;
+        let plan = self.plan_up_where(to, migrated_versions.clone(), |_| true)?;
+        migrated_versions.extend(plan.targets.iter().cloned());
+        let final_current_version = self.newest_of(migrated_versions);
+        Ok(MigrationDiff {
+            to_apply: plan.targets,
+            already_applied: plan.skipped,
+            final_current_version,
+        })
+    }
+
+    // Computes the `down` plan from the given current version against the given set of applied
+    // versions, restricted to the migrations accepted by `filter`.
+    fn plan_down_where<F>(&self, to: Option<Version>, from: Option<Version>,
+                          migrated_versions: &BTreeSet<Version>, filter: F)
+        -> Result<Plan, Error<T::Error>>
+        where F: Fn(&T::MigrationType) -> bool
+    {
+        let mut plan = Plan { targets: Vec::new(), skipped: Vec::new() };
+        if from.is_none() {
+            return Ok(plan);
+        }
+
+        // Rollback migrations from latest to oldest, dependents before their dependencies:
+        for version in self.ordered_versions()?.into_iter().rev() {
+            // Rollback the current version, and all versions downwards until the specified version
+            // (exclusive):
+            if !self.within(version, to, from) ||
+                !filter(&self.migrations[&version])
+            {
+                continue;
+            }
+
+            // Rollback only the migrations that are actually already migrated (in the case that
+            // some intermediary migrations were never executed).
+            if migrated_versions.contains(&version) {
+                plan.targets.push(version);
+            } else {
+                plan.skipped.push(version);
+            }
+        }
+
+        Ok(plan)
+    }
+
+    // Computes the `up` plan against the given set of applied versions, restricted to the
+    // migrations accepted by `filter`.
+    fn plan_up_where<F>(&self, to: Option<Version>, mut migrated_versions: BTreeSet<Version>,
+                        filter: F) -> Result<Plan, Error<T::Error>>
+        where F: Fn(&T::MigrationType) -> bool
+    {
+        let mut plan = Plan { targets: Vec::new(), skipped: Vec::new() };
+        for version in self.ordered_versions()? {
+            // Execute all versions upwards until the specified version (inclusive):
+            if !self.within(version, None, to) ||
+                !filter(&self.migrations[&version])
+            {
+                continue;
+            }
+
+            // Execute only the migrations that are actually not already migrated (in the case that
+            // some intermediary migrations were previously executed).
+            if migrated_versions.contains(&version) {
+                plan.skipped.push(version);
+            } else {
+                plan.targets.push(version);
+            }
+        }
+
+        // A dependency beyond the target version (or excluded by the filter) must already be
+        // applied:
+        for &version in &plan.targets {
+            for dependency in self.migrations[&version].dependencies() {
+                if !migrated_versions.contains(&dependency) {
+                    return Err(Error::MissingDependency(dependency));
+                }
+            }
+            migrated_versions.insert(version);
+        }
+
+        Ok(plan)
+    }
+
+    /// Rollback to the specified version (exclusive), or rollback to the state before any
+    /// registered migrations were applied if `None` is specified. Returns
+    /// `Error::DownMigrationsUnsupported` without reverting anything if the adapter does not
+    /// support reverting migrations. Returns the versions of the reverted migrations, in the
+    /// order in which they were reverted.
+    pub fn down(&mut self, to: Option<Version>) -> Result<Vec<Version>, Error<T::Error>> {
+        self.require_down_migrations()?;
+        self.with_lock(|migrator| {
+            let from = migrator.newest_version()?;
+            let migrated_versions = migrator.migrated_versions()?;
+            let plan = migrator.plan_down_where(to, from, &migrated_versions, |_| true)?;
+            migrator.skip_all(plan.skipped, "not applied");
+            migrator.revert_all(plan.targets.clone())?;
+            Ok(plan.targets)
+        })
+    }
+
+    /// Migrate to the specified version (inclusive). Every unapplied migration up to the version is
+    /// applied, including "retroactive" migrations with versions below the current version, such
+    /// as those registered after newer migrations were already applied (see `up_fill_gaps`).
+    /// Returns the versions of the applied migrations, in the order in which they were applied.
+    pub fn up(&mut self, to: Option<Version>) -> Result<Vec<Version>, Error<T::Error>> {
+        self.with_lock(|migrator| {
+            let migrated_versions = migrator.migrated_versions()?;
+            let plan = migrator.plan_up_where(to, migrated_versions, |_| true)?;
+            migrator.skip_all(plan.skipped, "already applied");
+            migrator.apply_all(plan.targets.clone())?;
+            Ok(plan.targets)
+        })
+    }
+
+    /// Applies only the retroactive migrations: the unapplied registered migrations with versions
+    /// below the current version, such as those merged from a branch after newer migrations were
+    /// already applied. Migrations above the current version are left pending.
+    pub fn up_fill_gaps(&mut self) -> Result<(), Error<T::Error>> {
+        self.with_lock(|migrator| {
+            let current = match migrator.newest_version()? {
+                Some(current) => current,
+                None => return Ok(()),
+            };
+            let migrated_versions = migrator.migrated_versions()?;
+            let plan = migrator.plan_up_where(None, migrated_versions, |migration| {
+                migrator.compare(migration.version(), current) == Ordering::Less
+            })?;
+            migrator.skip_all(plan.skipped, "already applied");
+            migrator.apply_all(plan.targets)
+        })
+    }
+
+    /// Like `up`, but returns the version and execution time of each applied migration, in the
+    /// order they were applied, such as to detect slow migrations. On failure, the timings of the
+    /// migrations applied before the failure are returned alongside the error. Without `std`,
+    /// every duration is zero.
+    #[allow(clippy::type_complexity)]
+    pub fn up_verbose(&mut self, to: Option<Version>)
+        -> Result<Vec<(Version, Duration)>, (Vec<(Version, Duration)>, Error<T::Error>)>
+    {
+        let mut timings = Vec::new();
+        let result = self.with_lock(|migrator| {
+            let migrated_versions = migrator.migrated_versions()?;
+            let plan = migrator.plan_up_where(to, migrated_versions, |_| true)?;
+            migrator.skip_all(plan.skipped, "already applied");
+            migrator.apply_all_within(plan.targets, None, &mut timings)
+        });
+        match result {
+            Ok(()) => Ok(timings),
+            Err(err) => Err((timings, err)),
+        }
+    }
+
+    /// Like `up`, but never fails: applies migrations one by one until the first failure, and
+    /// returns the versions of the migrations that were applied along with the error that stopped
+    /// the run, if any. The migrations applied before a failure are kept, even if the migrator is
+    /// configured to roll back on failure.
+    pub fn up_until_error(&mut self, to: Option<Version>)
+        -> (Vec<Version>, Option<Error<T::Error>>)
+    {
+        let rollback_on_failure = core::mem::replace(&mut self.rollback_on_failure, false);
+        let result = self.up_verbose(to);
+        self.rollback_on_failure = rollback_on_failure;
+        let (timings, err) = match result {
+            Ok(timings) => (timings, None),
+            Err((timings, err)) => (timings, Some(err)),
+        };
+        (timings.into_iter().map(|(version, _)| version).collect(), err)
+    }
+
+    /// Like `up`, but stops with `Error::Timeout` if the run has taken longer than the timeout by
+    /// the time the next migration is about to be applied. A migration that is already executing
+    /// is never interrupted.
+    #[cfg(feature = "std")]
+    pub fn up_with_timeout(&mut self, to: Option<Version>, timeout: Duration)
+        -> Result<(), Error<T::Error>>
+    {
+        let stopwatch = Stopwatch::start();
+        self.with_lock(|migrator| {
+            let migrated_versions = migrator.migrated_versions()?;
+            let plan = migrator.plan_up_where(to, migrated_versions, |_| true)?;
+            migrator.skip_all(plan.skipped, "already applied");
+            migrator.apply_all_within(plan.targets, Some((&stopwatch, timeout)), &mut Vec::new())
+        })
+    }
+
+    /// Like `up`, but marks the migrations as applied through `MarkableAdapter::mark_applied`
+    /// instead of executing them, such as to adopt a database whose schema was managed without
+    /// this crate. No transactions, checksums or hooks are involved. Returns the versions of the
+    /// migrations that were marked.
+    pub fn fake_up(&mut self, to: Option<Version>) -> Result<Vec<Version>, Error<T::Error>>
+        where T: MarkableAdapter
+    {
+        self.with_lock(|migrator| {
+            let migrated_versions = migrator.migrated_versions()?;
+            let plan = migrator.plan_up_where(to, migrated_versions, |_| true)?;
+            migrator.skip_all(plan.skipped, "already applied");
+            for &version in &plan.targets {
+                log_event!(info, "Marking migration as applied",
+                           source = migrator.source(), { version = version.get() };
+                           "{}Marking migration {:?} as applied", migrator.log_prefix(), version);
+                migrator.adapter.mark_applied(version).map_err(Error::adapter)?;
+            }
+            Ok(plan.targets)
+        })
+    }
+
+    /// Applies all pending migrations. Equivalent to `up(None)`.
+    pub fn up_to_latest(&mut self) -> Result<Vec<Version>, Error<T::Error>> {
+        self.up(None)
+    }
+
+    /// Applies all pending migrations up to the newest registered migration, which is
+    /// `last_version` unless a custom ordering is used. Unlike `up(None)`, whose range is
+    /// unbounded, the target is pinned to the newest migration registered at the time of the call.
+    pub fn migrate_to_latest_registered(&mut self) -> Result<Vec<Version>, Error<T::Error>> {
+        self.up(self.newest_registered())
+    }
+
+    /// Returns true if the current version is the highest registered version, or if no migrations
+    /// have been registered nor recorded.
+    pub fn is_up_to_date(&mut self) -> Result<bool, Error<T::Error>> {
+        Ok(self.newest_version()? == self.newest_registered())
+    }
+
+    // Returns the newest registered version according to the migrator's ordering.
+    fn newest_registered(&self) -> Option<Version> {
+        match self.comparator {
+            Some(_) => self.newest_of(self.migrations.keys().copied()),
+            None => self.last_version(),
+        }
+    }
+
+    /// Reverts all applied migrations, then clears the registry of migrations.
+    pub fn reset(&mut self) -> Result<(), Error<T::Error>> {
+        self.down(None)?;
+        self.migrations.clear();
+        Ok(())
+    }
+
+    /// Reverts all applied migrations, then applies all registered migrations.
+    pub fn reset_and_up(&mut self) -> Result<(), Error<T::Error>> {
+        self.with_lock(|migrator| {
+            migrator.down(None)?;
+            migrator.up(None).map(|_| ())
+        })
+    }
+
+    /// Reverts only the most recently applied migration. Returns `false` if there was nothing to
+    /// revert, and `Error::DownMigrationsUnsupported` if the adapter does not support reverting
+    /// migrations.
+    pub fn down_one(&mut self) -> Result<bool, Error<T::Error>> {
+        self.require_down_migrations()?;
+        self.with_lock(|migrator| {
+            let from = migrator.newest_version()?;
+            let migrated_versions = migrator.migrated_versions()?;
+            let plan = migrator.plan_down_where(None, from, &migrated_versions, |_| true)?;
+            match plan.targets.first() {
+                Some(&version) => migrator.revert_all(vec![version]).map(|_| true),
+                None => Ok(false),
+            }
+        })
+    }
+
+    /// Applies only the next pending migration. Returns `false` if there was nothing to apply.
+    pub fn up_one(&mut self) -> Result<bool, Error<T::Error>> {
+        self.with_lock(|migrator| {
+            let migrated_versions = migrator.migrated_versions()?;
+            let plan = migrator.plan_up_where(None, migrated_versions, |_| true)?;
+            match plan.targets.first() {
+                Some(&version) => migrator.apply_all(vec![version]).map(|_| true),
+                None => Ok(false),
+            }
+        })
+    }
+
+    /// Returns the migration that `up_one` would apply, or `None` if there is nothing to apply. No
+    /// migration is run, and the adapter is only queried.
+    pub fn peek_next_up(&mut self) -> Result<Option<&T::MigrationType>, Error<T::Error>> {
+        let migrated_versions = self.migrated_versions()?;
+        let plan = self.plan_up_where(None, migrated_versions, |_| true)?;
+        match plan.targets.first() {
+            Some(version) => Ok(self.migrations.get(version)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the migration that `down_one` would revert, or `None` if there is nothing to revert.
+    /// No migration is run, and the adapter is only queried.
+    pub fn peek_next_down(&mut self) -> Result<Option<&T::MigrationType>, Error<T::Error>> {
+        let from = self.newest_version()?;
+        let migrated_versions = self.migrated_versions()?;
+        let plan = self.plan_down_where(None, from, &migrated_versions, |_| true)?;
+        match plan.targets.first() {
+            Some(version) => Ok(self.migrations.get(version)),
+            None => Ok(None),
+        }
+    }
+
+    /// Performs the migration run described by the command.
+    pub fn run(&mut self, command: MigrationCommand) -> Result<(), Error<T::Error>> {
+        match command {
+            MigrationCommand::Up(to) => self.up(to).map(|_| ()),
+            MigrationCommand::Down(to) => self.down(to).map(|_| ()),
+            MigrationCommand::UpToLatest => self.up_to_latest().map(|_| ()),
+            MigrationCommand::Reset => self.reset_and_up(),
+            MigrationCommand::Step(steps) => {
+                self.with_lock(|migrator| {
+                    for _ in 0..steps.unsigned_abs() {
+                        let stepped =
+                            if steps > 0 { migrator.up_one()? } else { migrator.down_one()? };
+                        if !stepped {
+                            break;
+                        }
+                    }
+                    Ok(())
+                })
+            },
+        }
+    }
+
+    /// Like `down`, but only reverts the migrations belonging to the specified namespace.
+    pub fn down_namespace(&mut self, namespace: &str, to: Option<Version>)
+        -> Result<(), Error<T::Error>>
+    {
+        self.require_down_migrations()?;
+        self.with_lock(|migrator| {
+            let from = migrator.newest_version()?;
+            let migrated_versions = migrator.adapter.migrated_versions_in_namespace(namespace)
+                .map_err(Error::adapter)?;
+            let plan = migrator.plan_down_where(to, from, &migrated_versions, |migration| {
+                migration.namespace() == namespace
+            })?;
+            migrator.skip_all(plan.skipped, "not applied");
+            migrator.revert_all(plan.targets)
+        })
+    }
+
+    /// Like `up`, but only applies the migrations belonging to the specified namespace.
+    pub fn up_namespace(&mut self, namespace: &str, to: Option<Version>)
+        -> Result<(), Error<T::Error>>
+    {
+        self.with_lock(|migrator| {
+            let migrated_versions = migrator.adapter.migrated_versions_in_namespace(namespace)
+                .map_err(Error::adapter)?;
+            let plan = migrator.plan_up_where(to, migrated_versions, |migration| {
+                migration.namespace() == namespace
+            })?;
+            migrator.skip_all(plan.skipped, "already applied");
+            migrator.apply_all(plan.targets)
+        })
+    }
+
+    /// Like `down`, but only reverts the migrations tagged with at least one of the specified tags.
+    pub fn down_tagged(&mut self, tags: &[&str], to: Option<Version>)
+        -> Result<(), Error<T::Error>>
+    {
+        self.require_down_migrations()?;
+        self.with_lock(|migrator| {
+            let from = migrator.newest_version()?;
+            let migrated_versions = migrator.migrated_versions()?;
+            let plan = migrator.plan_down_where(to, from, &migrated_versions, |migration| {
+                migration.tags().iter().any(|tag| tags.contains(tag))
+            })?;
+            migrator.skip_all(plan.skipped, "not applied");
+            migrator.revert_all(plan.targets)
+        })
+    }
+
+    /// Like `up`, but only applies the migrations tagged with at least one of the specified tags.
+    pub fn up_tagged(&mut self, tags: &[&str], to: Option<Version>)
+        -> Result<(), Error<T::Error>>
+    {
+        self.with_lock(|migrator| {
+            let migrated_versions = migrator.migrated_versions()?;
+            let plan = migrator.plan_up_where(to, migrated_versions, |migration| {
+                migration.tags().iter().any(|tag| tags.contains(tag))
+            })?;
+            migrator.skip_all(plan.skipped, "already applied");
+            migrator.apply_all(plan.targets)
+        })
+    }
+
+    /// Like `up`, but only applies the migrations in the specified phase. Returns
+    /// `Error::MissingDependency` if a migration depends on an unapplied migration of another
+    /// phase. Returns the versions of the applied migrations, in the order in which they were
+    /// applied.
+    pub fn up_phase(&mut self, phase: MigrationPhase) -> Result<Vec<Version>, Error<T::Error>> {
+        self.with_lock(|migrator| {
+            let migrated_versions = migrator.migrated_versions()?;
+            let plan = migrator.plan_up_where(None, migrated_versions, |migration| {
+                migration.phase() == phase
+            })?;
+            migrator.skip_all(plan.skipped, "already applied");
+            migrator.apply_all(plan.targets.clone())?;
+            Ok(plan.targets)
+        })
+    }
+
+    /// Like `down`, but only reverts the applied migrations whose versions fall within the
+    /// specified range (inclusive).
+    pub fn down_range(&mut self, range: RangeInclusive<Version>) -> Result<(), Error<T::Error>> {
+        self.require_down_migrations()?;
+        self.with_lock(|migrator| {
+            let from = migrator.newest_version()?;
+            let migrated_versions = migrator.migrated_versions()?;
+            let plan = migrator.plan_down_where(None, from, &migrated_versions, |migration| {
+                range.contains(&migration.version())
+            })?;
+            migrator.skip_all(plan.skipped, "not applied");
+            migrator.revert_all(plan.targets)
+        })
+    }
+
+    /// Like `up`, but only applies the pending migrations whose versions fall within the specified
+    /// range (inclusive). Returns `Error::MissingDependency` if a migration depends on an
+    /// unapplied migration outside of the range.
+    pub fn up_range(&mut self, range: RangeInclusive<Version>) -> Result<(), Error<T::Error>> {
+        self.with_lock(|migrator| {
+            let migrated_versions = migrator.migrated_versions()?;
+            let plan = migrator.plan_up_where(None, migrated_versions, |migration| {
+                range.contains(&migration.version())
+            })?;
+            migrator.skip_all(plan.skipped, "already applied");
+            migrator.apply_all(plan.targets)
+        })
+    }
+
+    /// Applies the migrations with the specified versions in exactly the given order, skipping
+    /// those that are already applied. A version listed more than once is only applied the first
+    /// time. Returns `Error::VersionNotFound` before applying anything if any of the versions has
+    /// not been registered.
+    pub fn migrate_exact(&mut self, versions: &[Version]) -> Result<(), Error<T::Error>> {
+        if let Some(&version) = versions.iter().find(|&&v| !self.version_registered(v)) {
+            return Err(Error::VersionNotFound(version));
+        }
+
+        self.with_lock(|migrator| {
+            let mut migrated_versions = migrator.migrated_versions()?;
+            // A repeated version is applied by its first occurrence:
+            let (targets, skipped): (Vec<Version>, Vec<Version>) =
+                versions.iter().partition(|&&v| migrated_versions.insert(v));
+            migrator.skip_all(skipped, "already applied");
+            migrator.apply_all(targets)
+        })
+    }
+
+    /// Applies the migration with the specified version, regardless of whether it has already been
+    /// applied or of the state of any other migration. Returns `Error::VersionNotFound` if the
+    /// version has not been registered.
+    pub fn apply_specific(&mut self, version: Version) -> Result<(), Error<T::Error>> {
+        self.with_lock(|migrator| {
+            if !migrator.version_registered(version) {
+                return Err(Error::VersionNotFound(version));
+            }
+
+            migrator.apply_all(vec![version])
+        })
+    }
+
+    /// Reverts the migration with the specified version, regardless of whether it has been applied
+    /// or of the state of any other migration. Returns `Error::VersionNotFound` if the version has
+    /// not been registered.
+    pub fn revert_specific(&mut self, version: Version) -> Result<(), Error<T::Error>> {
+        self.require_down_migrations()?;
+        self.with_lock(|migrator| {
+            if !migrator.version_registered(version) {
+                return Err(Error::VersionNotFound(version));
+            }
+
+            migrator.revert_all(vec![version])
+        })
+    }
+
+    /// Brings the adapter into the state of a database that already has the migrations with the
+    /// specified versions applied, such as to set up a test. Each migration is passed straight to
+    /// the adapter's `apply_migration` in ascending order of version, without checking for
+    /// dependencies or prior application, and without transactions, checksums, logging or progress
+    /// events. Returns `Error::VersionNotFound` before applying anything if any version has not
+    /// been registered.
+    pub fn seed_versions(&mut self, versions: BTreeSet<Version>) -> Result<(), Error<T::Error>> {
+        if let Some(&version) = versions.iter().find(|v| !self.version_registered(**v)) {
+            return Err(Error::VersionNotFound(version));
+        }
+
+        for version in versions {
+            let migration = &self.migrations[&version];
+            self.adapter.apply_migration(migration).map_err(Error::adapter)?;
+        }
+        Ok(())
+    }
+
+    // Fails with `Error::DownMigrationsUnsupported` if the adapter cannot revert migrations.
+    fn require_down_migrations(&self) -> Result<(), Error<T::Error>> {
+        if self.adapter.supports_down_migrations() {
+            Ok(())
+        } else {
+            Err(Error::DownMigrationsUnsupported)
+        }
+    }
+
+    // Runs the given closure while holding the adapter's lock, if the migrator was created with
+    // `new_locked`. Nested calls reuse the lock that is already held.
+    fn with_lock<F, R>(&mut self, run: F) -> Result<R, Error<T::Error>>
+        where F: FnOnce(&mut Migrator<T>) -> Result<R, Error<T::Error>>
+    {
+        let release = match self.locks {
+            Some(ref locks) if !self.lock_held => {
+                (locks.acquire)(&mut self.adapter).map_err(|_| Error::LockNotAcquired)?;
+                locks.release
+            },
+            _ => return run(self),
+        };
+
+        self.lock_held = true;
+        let guard = LockGuard { migrator: self, release };
+        run(&mut *guard.migrator)
+    }
+
+    // Reports each of the given versions as skipped for the given reason.
+    fn skip_all(&self, versions: Vec<Version>, reason: &'static str) {
+        for version in versions {
+            self.notify(ProgressEvent::MigrationSkipped { version, reason });
+        }
+    }
+
+    // Reverts the migrations with the given versions in order as a single run, surrounded by the
+    // adapter's batch hooks.
+    fn revert_all(&mut self, versions: Vec<Version>) -> Result<(), Error<T::Error>> {
+        self.adapter.before_migration_batch().map_err(Error::adapter)?;
+        let result = if self.runs_batched() {
+            self.execute_batch(versions, Direction::Down)
+        } else {
+            self.revert_each(versions)
+        };
+        self.finish_batch(result)
+    }
+
+    // Applies the migrations with the given versions in order as a single run, surrounded by the
+    // adapter's batch hooks.
+    fn apply_all(&mut self, versions: Vec<Version>) -> Result<(), Error<T::Error>> {
+        if self.runs_batched() {
+            self.adapter.before_migration_batch().map_err(Error::adapter)?;
+            let result = self.execute_batch(versions, Direction::Up);
+            return self.finish_batch(result);
+        }
+        self.apply_all_within(versions, None, &mut Vec::new())
+    }
 
-/// The version type alias used to uniquely reference migrations.
-pub type Version = i64;
+    // Returns true if runs of migrations are handed to the adapter's `batch_apply` and
+    // `batch_revert`. Retrying and rolling back after a failure need to know which migration
+    // failed, so they require the migrations to be run one by one.
+    fn runs_batched(&self) -> bool {
+        self.adapter.supports_batch() && self.retry_policy.is_none() && !self.rollback_on_failure
+    }
 
-/// All migrations will implement this trait, and a migration trait specific to the chosen adapter.
-/// This trait defines the metadata for tracking migration sequence and for human reference.
-pub trait Migration {
-    /// An ordered (but not necessarily sequential), unique identifier for this migration.
-    /// Registered migrations will be applied in ascending order by version.
-    fn version(&self) -> Version;
+    // Applies or reverts the migrations with the given versions in order with a single call to the
+    // adapter's `batch_apply` or `batch_revert`, within a single transaction if configured. The
+    // failing migration is unknown, so a failure is reported with `ErrorContext::Batch`, and every
+    // migration is reported with the duration of the whole batch.
+    fn execute_batch(&mut self, versions: Vec<Version>, direction: Direction)
+        -> Result<(), Error<T::Error>>
+    {
+        if versions.is_empty() {
+            return Ok(());
+        }
 
-    /// A message describing the effects of this migration.
-    fn description(&self) -> String;
-}
+        let descriptions: Vec<String> =
+            versions.iter().map(|version| self.migrations[version].description()).collect();
+        #[cfg(feature = "tracing")]
+        let _batch_span = tracing::info_span!("migration_batch", versions = ?versions,
+                                              direction = ?direction).entered();
+        self.begin_transaction()?;
+        for (&version, description) in versions.iter().zip(&descriptions) {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::info_span!("migration", version = %version,
+                                            description = %description,
+                                            direction = ?direction).entered();
+            let description = description.clone();
+            if direction.is_up() {
+                log_event!(info, "Applying migration",
+                           source = self.source(),
+                           { version = version.get(), description = &*description };
+                           "{}Applying migration {:?}: {}", self.log_prefix(), version,
+                           description);
+                self.notify(ProgressEvent::BeforeApply { version, description });
+            } else {
+                log_event!(info, "Reverting migration",
+                           source = self.source(),
+                           { version = version.get(), description = &*description };
+                           "{}Reverting migration {:?}: {}", self.log_prefix(), version,
+                           description);
+                self.notify(ProgressEvent::BeforeRevert { version, description });
+            }
+        }
 
-/// A migration's direction.
-#[derive(Debug)]
-pub enum Direction {
-    Down,
-    Up,
-}
+        let start = Stopwatch::start();
+        let registry = &self.migrations;
+        let migrations: Vec<&T::MigrationType> =
+            versions.iter().map(|version| &registry[version]).collect();
+        let result = match direction {
+            Direction::Up => self.adapter.batch_apply(&migrations),
+            Direction::Down => self.adapter.batch_revert(&migrations),
+        };
+        let duration = start.elapsed();
+        #[cfg(feature = "event-log")]
+        for (&version, description) in versions.iter().zip(&descriptions) {
+            self.event_log.push(MigrationEvent {
+                timestamp: SystemTime::now(),
+                version,
+                description: description.clone(),
+                direction,
+                success: result.is_ok(),
+            });
+        }
+        if let Err(err) = result {
+            let context = ErrorContext::Batch { versions: versions.clone(), direction };
+            return Err(self.rollback_transaction(Error::Adapter { context, error: err }));
+        }
 
-/// An all-encompassing error type that can be returned during interaction with the migrator
-/// adapter.
-#[derive(Debug)]
-pub enum Error<E> {
-    /// A generic error that occurred while interacting with the adapter.
-    Adapter(E),
-    /// An error that arose from the adapter specifically during a migration's execution.
-    Migration {
-        /// The version of the migration that failed.
-        version: Version,
-        /// The description of the migration that failed.
-        description: String,
-        /// The direction in which the failed migration was ran.
-        direction: Direction,
-        /// The underlying error from the adapter.
-        error: E,
+        if direction.is_up() {
+            for &version in &versions {
+                if let Some(checksum) = self.migrations[&version].checksum() {
+                    if let Err(err) = self.adapter.record_checksum(version, checksum) {
+                        return Err(self.rollback_transaction(Error::adapter(err)));
+                    }
+                }
+            }
+        }
+
+        self.commit_transaction()?;
+        for (version, description) in versions.into_iter().zip(descriptions) {
+            if direction.is_up() {
+                self.adapter.on_migration_applied(version, &description, duration)
+                    .map_err(Error::adapter)?;
+                self.notify(ProgressEvent::AfterApply { version, description, duration });
+            } else {
+                self.adapter.on_migration_reverted(version, &description, duration)
+                    .map_err(Error::adapter)?;
+                self.notify(ProgressEvent::AfterRevert { version, description, duration });
+            }
+        }
+
+        Ok(())
     }
-}
 
-impl<E: StdError> StdError for Error<E> {
-    fn description(&self) -> &str {
-        match *self {
-            Error::Adapter(ref err) => err.description(),
-            Error::Migration { ref error, .. } => error.description(),
+    // Like `apply_all`, but stops with `Error::Timeout` before applying a migration once the given
+    // stopwatch has exceeded the given timeout, and records how long each applied migration took.
+    fn apply_all_within(&mut self, versions: Vec<Version>, timeout: Option<(&Stopwatch, Duration)>,
+                        timings: &mut Vec<(Version, Duration)>)
+        -> Result<(), Error<T::Error>>
+    {
+        self.adapter.before_migration_batch().map_err(Error::adapter)?;
+        let result = self.apply_each(versions, timeout, timings);
+        self.finish_batch(result)
+    }
+
+    // Calls the adapter's `after_migration_batch` hook with the outcome of the run. An error from
+    // the hook is only reported if the run itself succeeded.
+    fn finish_batch(&mut self, result: Result<(), Error<T::Error>>) -> Result<(), Error<T::Error>> {
+        match self.adapter.after_migration_batch(result.is_ok()) {
+            Ok(()) => result,
+            Err(err) => {
+                if result.is_err() {
+                    log_event!(warn, "Failed to finish migration batch after a failed run");
+                }
+                result.and(Err(Error::adapter(err)))
+            },
         }
     }
 
-    fn cause(&self) -> Option<&StdError> {
-        match *self {
-            Error::Adapter(ref err) => Some(err),
-            Error::Migration { ref error, .. } => Some(error),
+    // Reverts the migrations with the given versions in order, stopping at the first failure.
+    fn revert_each(&mut self, versions: Vec<Version>) -> Result<(), Error<T::Error>> {
+        for version in versions {
+            let description = self.migrations[&version].description();
+            #[cfg(feature = "tracing")]
+            let _span = tracing::info_span!("migration", version = %version,
+                                            description = %description,
+                                            direction = ?Direction::Down).entered();
+            self.begin_transaction()?;
+            log_event!(info, "Reverting migration",
+                       source = self.source(),
+                       { version = version.get(), description = &*description };
+                       "{}Reverting migration {:?}: {}", self.log_prefix(), version, description);
+            self.notify(ProgressEvent::BeforeRevert { version, description: description.clone() });
+            let start = Stopwatch::start();
+            self.execute(version, &description, Direction::Down)?;
+            let duration = start.elapsed();
+            self.commit_transaction()?;
+            self.adapter.on_migration_reverted(version, &description, duration)
+                .map_err(Error::adapter)?;
+            self.notify(ProgressEvent::AfterRevert { version, description, duration });
         }
+
+        Ok(())
     }
-}
 
-impl<E: StdError> Display for Error<E> {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
-        match *self {
-            Error::Adapter(ref err) => write!(f, "Adataper error: {}", err),
-            Error::Migration { ref description, ref error, .. } => {
-                write!(f, "Error running migration {}, error: {}", description, error)
+    // Applies the migrations with the given versions in order, stopping at the first failure (and
+    // rolling back, if configured to do so).
+    fn apply_each(&mut self, versions: Vec<Version>, timeout: Option<(&Stopwatch, Duration)>,
+                  timings: &mut Vec<(Version, Duration)>)
+        -> Result<(), Error<T::Error>>
+    {
+        let mut applied = Vec::new();
+        for version in versions {
+            if let Some((stopwatch, timeout)) = timeout {
+                let elapsed = stopwatch.elapsed();
+                if elapsed > timeout {
+                    return Err(Error::Timeout { elapsed, last_applied: applied.last().copied() });
+                }
+            }
+
+            let description = self.migrations[&version].description();
+            #[cfg(feature = "tracing")]
+            let _span = tracing::info_span!("migration", version = %version,
+                                            description = %description,
+                                            direction = ?Direction::Up).entered();
+            self.begin_transaction()?;
+            let checksum = self.migrations[&version].checksum();
+            log_event!(info, "Applying migration",
+                       source = self.source(),
+                       { version = version.get(), description = &*description };
+                       "{}Applying migration {:?}: {}", self.log_prefix(), version, description);
+            self.notify(ProgressEvent::BeforeApply { version, description: description.clone() });
+            let start = Stopwatch::start();
+            if let Err(apply_error) = self.execute(version, &description, Direction::Up) {
+                // `execute` has already rolled back the failing migration's transaction:
+                if self.transactions.is_some() {
+                    let rollback_failed = matches!(apply_error, Error::RollbackFailed { .. });
+                    if !self.rollback_on_failure || rollback_failed {
+                        return Err(apply_error);
+                    }
+                    return self.roll_back_run(applied, apply_error);
+                }
+
+                if !self.rollback_on_failure {
+                    return Err(apply_error);
+                }
+
+                // Without a transaction, the failing migration may have been partially applied:
+                applied.push(version);
+                return self.roll_back_run(applied, apply_error);
+            }
+            let duration = start.elapsed();
+
+            if let Some(checksum) = checksum {
+                if let Err(err) = self.adapter.record_checksum(version, checksum) {
+                    return Err(self.rollback_transaction(Error::adapter(err)));
+                }
+            }
+
+            self.commit_transaction()?;
+            self.adapter.on_migration_applied(version, &description, duration)
+                .map_err(Error::adapter)?;
+            self.notify(ProgressEvent::AfterApply { version, description, duration });
+            timings.push((version, duration));
+            applied.push(version);
+        }
+
+        Ok(())
+    }
+
+    // Applies or reverts the migration, retrying according to the retry policy. With
+    // transactions, each failed attempt is rolled back here, including the last one, and a new
+    // transaction is begun before the next attempt. Callers must not roll back again on failure.
+    fn execute(&mut self, version: Version, description: &str, direction: Direction)
+        -> Result<(), Error<T::Error>>
+    {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let migration = &self.migrations[&version];
+            let result = match direction {
+                Direction::Up => self.adapter.apply_migration(migration),
+                Direction::Down => self.adapter.revert_migration(migration),
+            };
+            #[cfg(feature = "event-log")]
+            self.event_log.push(MigrationEvent {
+                timestamp: SystemTime::now(),
+                version,
+                description: description.into(),
+                direction,
+                success: result.is_ok(),
+            });
+            let err = match result {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+
+            let policy = match self.retry_policy {
+                Some(policy) if attempts < policy.max_attempts => policy,
+                retry_policy => {
+                    let context = ErrorContext::Migration {
+                        version,
+                        description: description.into(),
+                        label: migration.label().map(String::from),
+                        direction,
+                    };
+                    let error = match retry_policy {
+                        Some(_) => Error::MaxRetriesExceeded { context, attempts, last_error: err },
+                        None => Error::Adapter { context, error: err },
+                    };
+                    return Err(self.rollback_transaction(error));
+                },
+            };
+
+            let delay = policy.backoff.delay(attempts);
+            log_event!(warn, "Retrying migration",
+                       source = self.source(),
+                       { version = version.get(), attempt = attempts + 1, delay:? = delay };
+                       "{}Retrying migration {:?} in {:?} (attempt {})", self.log_prefix(),
+                       version, delay, attempts + 1);
+            let error = self.rollback_transaction(Error::adapter(err));
+            if let Error::RollbackFailed { .. } = error {
+                return Err(error);
+            }
+            #[cfg(feature = "std")]
+            std::thread::sleep(delay);
+            self.begin_transaction()?;
+        }
+    }
+
+    // Reverts the migrations applied during a failed run, latest first, and returns the error that
+    // caused the failure.
+    fn roll_back_run(&mut self, mut applied: Vec<Version>, apply_error: Error<T::Error>)
+        -> Result<(), Error<T::Error>>
+    {
+        log_event!(warn, "Rolling back migrations after failure",
+                   source = self.source(), { count = applied.len() };
+                   "{}Rolling back {} migration(s) after failure", self.log_prefix(),
+                   applied.len());
+        applied.reverse();
+        match self.revert_each(applied) {
+            Ok(()) => Err(apply_error),
+            Err(revert_error) => Err(Error::RollbackFailed {
+                apply_error: Box::new(apply_error),
+                revert_error: Box::new(revert_error),
+            }),
+        }
+    }
+
+    // Returns the prefix of the migrator's log messages, which names the source of its migrations.
+    // With `kv-logging`, the source is logged as a key-value pair instead.
+    #[cfg_attr(all(feature = "kv-logging", not(feature = "tracing")), allow(dead_code))]
+    fn log_prefix(&self) -> String {
+        match self.source {
+            Some(ref source) => format!("[{}] ", source),
+            None => String::new(),
+        }
+    }
+
+    // Begins a transaction, if the migrator was created with `new_transactional`.
+    fn begin_transaction(&mut self) -> Result<(), Error<T::Error>> {
+        match self.transactions {
+            Some(ref transactions) => {
+                (transactions.begin)(&mut self.adapter).map_err(Error::adapter)
+            },
+            None => Ok(()),
+        }
+    }
+
+    // Commits the current transaction, if the migrator was created with `new_transactional`.
+    fn commit_transaction(&mut self) -> Result<(), Error<T::Error>> {
+        match self.transactions {
+            Some(ref transactions) => {
+                (transactions.commit)(&mut self.adapter).map_err(Error::adapter)
+            },
+            None => Ok(()),
+        }
+    }
+
+    // Rolls back the current transaction after the given error, if the migrator was created with
+    // `new_transactional`. Returns the error to report.
+    fn rollback_transaction(&mut self, error: Error<T::Error>) -> Error<T::Error> {
+        let result = match self.transactions {
+            Some(ref transactions) => (transactions.rollback)(&mut self.adapter),
+            None => Ok(()),
+        };
+
+        match result {
+            Ok(()) => error,
+            Err(err) => Error::RollbackFailed {
+                apply_error: Box::new(error),
+                revert_error: Box::new(Error::adapter(err)),
             },
         }
     }
+
+    // Passes the event to the progress callback, if one is set.
+    fn notify(&self, event: ProgressEvent) {
+        if let Some(ref callback) = self.progress_callback {
+            callback(event);
+        }
+    }
+
+    /// Checks that every applied, registered migration still has the checksum that was recorded
+    /// when it was applied. Migrations without a checksum, or without a recorded checksum, are
+    /// skipped.
+    pub fn verify(&mut self) -> Result<(), Error<T::Error>> {
+        let migrated_versions = self.migrated_versions()?;
+        let applied = self.migrations.iter().filter(|&(v, _)| migrated_versions.contains(v));
+
+        for (&version, migration) in applied {
+            let actual = match migration.checksum() {
+                Some(checksum) => checksum,
+                None => continue,
+            };
+
+            match self.adapter.verify_checksum(version).map_err(Error::adapter)? {
+                Some(expected) if expected != actual => {
+                    return Err(Error::ChecksumMismatch { version, expected, actual });
+                },
+                _ => {},
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the applied migrations form a contiguous prefix of the registered migrations, in
+    /// the order in which `up` applies them. Otherwise, such as after applying migrations out of
+    /// order with `apply_specific`, returns `Error::OrderViolation` with the registered migrations
+    /// that were skipped over and the applied migrations that are not registered.
+    pub fn verify_order(&mut self) -> Result<(), Error<T::Error>> {
+        let migrated_versions = self.migrated_versions()?;
+        let registered = migrated_versions.iter().copied().filter(|v| self.version_registered(*v));
+        let newest = self.newest_of(registered);
+        let gaps: Vec<Version> = self.migrations.keys()
+            .filter(|v| !migrated_versions.contains(v) &&
+                        newest.is_some_and(|newest| self.compare(**v, newest) == Ordering::Less))
+            .cloned()
+            .collect();
+        let unexpected: Vec<Version> =
+            migrated_versions.into_iter().filter(|v| !self.version_registered(*v)).collect();
+
+        if gaps.is_empty() && unexpected.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::OrderViolation { gaps, unexpected })
+        }
+    }
+
+    /// Renders a human-readable table of the registered migrations, one line per migration with its
+    /// version, `[applied]` or `[pending]` status, and description. This requires querying the
+    /// adapter, which is why `Migrator` does not implement `Display`.
+    pub fn to_string_with_state(&mut self) -> Result<String, Error<T::Error>> {
+        let migrated_versions = self.migrated_versions()?;
+        let mut table = String::new();
+        for (version, migration) in &self.migrations {
+            let state = if migrated_versions.contains(version) { "[applied]" } else { "[pending]" };
+            table.push_str(&format!("{} {} {}\n", version, state, migration.description()));
+        }
+        Ok(table)
+    }
+
+    /// Formats the plan of `up` for the specified version as a table with aligned columns, for
+    /// printing to a terminal. The migrations that would be applied are listed as pending, in the
+    /// order that they would be applied, after the migrations within the range that are already
+    /// applied. The descriptions of labeled migrations are prefixed with their label, and followed
+    /// by the author and ticket of the migration, if any. No migrations are executed, and the
+    /// output only depends on the registered migrations and the adapter's state.
+    pub fn explain_up(&mut self, to: Option<Version>) -> Result<String, Error<T::Error>> {
+        let migrated_versions = self.migrated_versions()?;
+        let plan = self.plan_up_where(to, migrated_versions, |_| true)?;
+        let rows = plan.skipped.iter()
+            .map(|v| (*v, explain_description(&self.migrations[v]), MigrationState::Applied))
+            .chain(plan.targets.iter()
+                .map(|v| (*v, explain_description(&self.migrations[v]), MigrationState::Pending)))
+            .collect::<Vec<_>>();
+        Ok(format_table(&rows))
+    }
+
+    /// Formats the state of every registered or applied migration, as returned by `status`, as a
+    /// table with aligned columns, for printing to a terminal. The descriptions of registered
+    /// migrations include their label, author and ticket, as with `explain_up`.
+    pub fn explain_status(&mut self) -> Result<String, Error<T::Error>> {
+        let rows = self.status()?.into_iter()
+            .map(|status| match self.migrations.get(&status.version) {
+                Some(migration) => (status.version, explain_description(migration), status.state),
+                None => (status.version, status.description, status.state),
+            })
+            .collect::<Vec<_>>();
+        Ok(format_table(&rows))
+    }
+
+    /// Migrate to the specified version, by reverting the applied, registered migrations newer than
+    /// it and then applying the pending migrations up to it (inclusive). If `None` is specified,
+    /// all registered migrations are rolled back. Returns `Error::VersionNotFound` if the target
+    /// version has not been registered. Returns the versions of the applied or reverted
+    /// migrations, in the order in which they were run.
+    pub fn migrate_to(&mut self, target: Option<Version>) -> Result<Vec<Version>, Error<T::Error>> {
+        self.with_lock(|migrator| {
+            let target_version = match target {
+                Some(version) => version,
+                None => return migrator.down(None),
+            };
+
+            if !migrator.version_registered(target_version) {
+                return Err(Error::VersionNotFound(target_version));
+            }
+
+            // Applied versions that are no longer registered cannot be reverted, so only the
+            // registered ones decide whether to migrate downwards:
+            let migrated_versions = migrator.migrated_versions()?;
+            let newer_applied = migrated_versions.into_iter().any(|version| {
+                migrator.version_registered(version)
+                    && migrator.compare(version, target_version) == Ordering::Greater
+            });
+            let mut versions = if newer_applied { migrator.down(target)? } else { Vec::new() };
+            versions.extend(migrator.up(target)?);
+            Ok(versions)
+        })
+    }
+
+    /// Serializes the versions and descriptions of the registered migrations as a JSON array of
+    /// `{ "version": .., "description": .. }` objects, sorted by version in ascending order. This
+    /// allows tooling to inspect the migrations contained in a binary without running them.
+    #[cfg(feature = "serde")]
+    pub fn registry_to_json(&self) -> String {
+        let entries: Vec<RegistryEntry> = self.migrations.iter()
+            .map(|(&version, migration)| {
+                RegistryEntry { version, description: migration.description() }
+            })
+            .collect();
+        serde_json::to_string(&entries).expect("registry entries are always serializable")
+    }
 }
 
-/// Efficiently implement the `Migration` trait for a given type.
-///
-/// ## Example
-///
-/// ```rust
-/// # #[macro_use]
-/// # extern crate schemamama;
-/// struct MyMigration;
-/// migration!(MyMigration, 100, "create some lovely database tables");
-///
-/// # fn main() {
-/// use schemamama::Migration;
-/// let m = MyMigration;
-/// assert_eq!(m.version(), 100);
-/// assert_eq!(m.description(), "create some lovely database tables");
-/// # }
-/// ```
-#[macro_export]
-macro_rules! migration {
-    ($ty:ident, $version:expr, $description:expr) => {
-        impl $crate::Migration for $ty {
-            fn version(&self) -> $crate::Version { $version }
-            fn description(&self) -> String { $description.into() }
+#[cfg(feature = "serde")]
+impl<T: Adapter<MigrationType = dyn Migration>> Migrator<T> {
+    /// Reconstructs a metadata-only migrator from the output of `registry_to_json`. Each migration
+    /// is a stub that carries only its version and description, which is enough to compare the
+    /// registry against the adapter's state (e.g. with `status` or `check_integrity`), but which
+    /// the adapter has no statements to execute for.
+    pub fn registry_from_json(adapter: T, json: &str) -> Result<Migrator<T>, serde_json::Error> {
+        let entries: Vec<RegistryEntry> = serde_json::from_str(json)?;
+        let mut migrator = Migrator::new(adapter);
+        for entry in entries {
+            migrator.migrations.insert(entry.version, Box::new(entry));
         }
+        Ok(migrator)
     }
 }
 
-/// Use this trait to connect the migrator to your chosen database technology.
-pub trait Adapter {
-    /// An alias to a specific trait that extends `Migration`. Typically, the aforementioned trait
-    /// will declare functions that the adapter will use to migrate upwards and downwards.
-    type MigrationType: Migration + ?Sized;
+// A registered migration as serialized by `Migrator::registry_to_json`, which doubles as the stub
+// migration restored by `Migrator::registry_from_json`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RegistryEntry {
+    version: Version,
+    description: String,
+}
 
-    /// An adapter-specific error type that can be returned from any of this trait's methods.
-    type Error;
+#[cfg(feature = "serde")]
+impl Migration for RegistryEntry {
+    fn version(&self) -> Version {
+        self.version
+    }
 
-    /// Returns the latest migration version, or `None` if no migrations have been recorded.
-    fn current_version(&self) -> Result<Option<Version>, Self::Error>;
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+}
 
-    /// Returns a set of the versions of all of the currently applied migrations.
-    fn migrated_versions(&self) -> Result<BTreeSet<Version>, Self::Error>;
+impl<'a, T: Adapter> IntoIterator for &'a Migrator<T> {
+    type Item = (&'a Version, &'a T::MigrationType);
+    type IntoIter = Iter<'a, T::MigrationType>;
 
-    /// Applies the specified migration.
-    fn apply_migration(&self, migration: &Self::MigrationType) -> Result<(), Self::Error>;
+    /// Iterates over the registered migrations in ascending order of version.
+    fn into_iter(self) -> Iter<'a, T::MigrationType> {
+        self.iter()
+    }
+}
 
-    /// Reverts the specified migration.
-    fn revert_migration(&self, migration: &Self::MigrationType) -> Result<(), Self::Error>;
+/// An iterator over the registered migrations of a `Migrator`, in ascending order of version.
+pub struct Iter<'a, M: Migration + ?Sized> {
+    inner: registry::Iter<'a, M>,
 }
 
-/// Maintains an ordered collection of migrations to utilize.
-pub struct Migrator<T: Adapter> {
-    adapter: T,
-    migrations: BTreeMap<Version, Box<T::MigrationType>>
+impl<'a, M: Migration + ?Sized> Iterator for Iter<'a, M> {
+    type Item = (&'a Version, &'a M);
+
+    fn next(&mut self) -> Option<(&'a Version, &'a M)> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
 }
 
-impl<T: Adapter> Migrator<T> {
-    /// Create a migrator with a given adapter.
-    pub fn new(adapter: T) -> Migrator<T> {
-        Migrator { adapter: adapter, migrations: BTreeMap::new() }
+impl<'a, M: Migration + ?Sized> DoubleEndedIterator for Iter<'a, M> {
+    fn next_back(&mut self) -> Option<(&'a Version, &'a M)> {
+        self.inner.next_back()
     }
+}
 
-    /// Get a reference to the adapter.
-    pub fn adapter(&self) -> &T {
-        &self.adapter
+impl<'a, M: Migration + ?Sized> ExactSizeIterator for Iter<'a, M> {}
+
+/// A mutable iterator over the registered migrations of a `Migrator`, in ascending order of
+/// version.
+pub struct IterMut<'a, M: Migration + ?Sized> {
+    inner: registry::IterMut<'a, M>,
+}
+
+impl<'a, M: Migration + ?Sized> Iterator for IterMut<'a, M> {
+    type Item = (&'a Version, &'a mut M);
+
+    fn next(&mut self) -> Option<(&'a Version, &'a mut M)> {
+        self.inner.next()
     }
 
-    /// Register a migration. If a migration with the same version is already registered, a warning
-    /// is logged and the registration fails.
-    pub fn register(&mut self, migration: Box<T::MigrationType>) {
-        let version = migration.version();
-        if self.version_registered(version) {
-            warn!("Migration with version {:?} is already registered", version);
-        } else {
-            self.migrations.insert(version, migration);
-        }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
     }
+}
 
-    /// Returns true is a migration with the provided version has been registered.
-    pub fn version_registered(&self, version: Version) -> bool {
-        self.migrations.contains_key(&version)
+impl<'a, M: Migration + ?Sized> DoubleEndedIterator for IterMut<'a, M> {
+    fn next_back(&mut self) -> Option<(&'a Version, &'a mut M)> {
+        self.inner.next_back()
     }
+}
 
-    /// Returns the set of all registered migration versions.
-    pub fn registered_versions(&self) -> BTreeSet<Version> {
-        self.migrations.keys().cloned().collect()
+impl<'a, M: Migration + ?Sized> ExactSizeIterator for IterMut<'a, M> {}
+
+/// Registers each migration with `register_or_warn`.
+impl<T: Adapter> Extend<Box<T::MigrationType>> for Migrator<T> {
+    fn extend<I>(&mut self, migrations: I) where I: IntoIterator<Item = Box<T::MigrationType>> {
+        for migration in migrations {
+            self.register_or_warn(migration);
+        }
     }
+}
 
-    /// Returns the lowest version of the registered migrations, or `None` if no migrations have
-    /// been registered.
-    pub fn first_version(&self) -> Option<Version> {
-        self.migrations.keys().next().map(|v| *v)
+/// Creates a migrator with a default adapter and no registered migrations.
+impl<T: Adapter + Default> Default for Migrator<T> {
+    fn default() -> Migrator<T> {
+        Migrator::new(T::default())
     }
+}
 
-    /// Returns the highest version of the registered migrations, or `None` if no migrations have
-    /// been registered.
-    pub fn last_version(&self) -> Option<Version> {
-        self.migrations.keys().last().map(|v| *v)
+/// Collects migrations into a migrator with a default adapter. See
+/// `Migrator::with_adapter_and_iter` for adapters that cannot be defaulted.
+impl<T: Adapter + Default> FromIterator<Box<T::MigrationType>> for Migrator<T> {
+    fn from_iter<I>(migrations: I) -> Migrator<T>
+        where I: IntoIterator<Item = Box<T::MigrationType>>
+    {
+        Migrator::with_adapter_and_iter(T::default(), migrations)
     }
+}
 
-    /// Returns the latest migration version, or `None` if no migrations have been recorded.
-    pub fn current_version(&self) -> Result<Option<Version>, Error<T::Error>> {
-        match self.adapter.current_version() {
-            Ok(ver) => Ok(ver),
-            Err(err) => Err(Error::Adapter(err)),
+/// Returns the registered migration with the provided version.
+///
+/// # Panics
+///
+/// Panics if no migration with the version has been registered. Use `Migrator::find_migration`
+/// for a fallible lookup.
+impl<T: Adapter> Index<Version> for Migrator<T> {
+    type Output = T::MigrationType;
+
+    fn index(&self, version: Version) -> &T::MigrationType {
+        match self.migrations.get(&version) {
+            Some(migration) => migration,
+            None => panic!("no migration registered with version {}", version),
         }
     }
+}
 
-    /// Returns a set of the versions of all of the currently applied migrations.
-    pub fn migrated_versions(&self) -> Result<BTreeSet<Version>, Error<T::Error>> {
-        match self.adapter.migrated_versions() {
-            Ok(vers) => Ok(vers),
-            Err(err) => Err(Error::Adapter(err)),
+/// Returns the registered migration at the provided position in ascending order of version (`0`
+/// being the migration with the lowest version).
+///
+/// # Panics
+///
+/// Panics if fewer migrations have been registered. Use `Migrator::migration_at` for a fallible
+/// lookup.
+impl<T: Adapter> Index<usize> for Migrator<T> {
+    type Output = T::MigrationType;
+
+    fn index(&self, position: usize) -> &T::MigrationType {
+        match self.migration_at(position) {
+            Some(migration) => migration,
+            None => panic!("no migration registered at position {} (registered: {})", position,
+                           self.migrations.len()),
         }
     }
+}
 
-    /// Rollback to the specified version (exclusive), or rollback to the state before any
-    /// registered migrations were applied if `None` is specified.
-    pub fn down(&self, to: Option<Version>) -> Result<(), Error<T::Error>> {
-        let from = try!(self.current_version());
-        if from.is_none() {
-            return Ok(());
+/// Prints the adapter and the description of each registered migration, so that the migrations
+/// themselves need not implement `Debug`.
+impl<T: Adapter + fmt::Debug> fmt::Debug for Migrator<T> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        let migrations: BTreeMap<Version, String> = self.migrations.iter()
+            .map(|(&version, migration)| (version, migration.description()))
+            .collect();
+        f.debug_struct("Migrator")
+            .field("adapter", &self.adapter)
+            .field("migrations", &migrations)
+            .finish()
+    }
+}
+
+/// A migrator can be cloned if its adapter and its boxed migrations can be cloned. Boxed trait
+/// objects are not `Clone` on their own; the usual remedy is a `clone_box` method on the
+/// adapter-specific migration trait:
+///
+/// ```rust
+/// # use schemamama::Migration;
+/// trait MyMigration: Migration {
+///     fn clone_box(&self) -> Box<dyn MyMigration>;
+/// }
+///
+/// impl<M: Migration + Clone + 'static> MyMigration for M {
+///     fn clone_box(&self) -> Box<dyn MyMigration> {
+///         Box::new(self.clone())
+///     }
+/// }
+///
+/// impl Clone for Box<dyn MyMigration> {
+///     fn clone(&self) -> Box<dyn MyMigration> {
+///         (**self).clone_box()
+///     }
+/// }
+/// ```
+impl<T> Clone for Migrator<T> where T: Adapter + Clone, Box<T::MigrationType>: Clone {
+    fn clone(&self) -> Migrator<T> {
+        Migrator {
+            adapter: self.adapter.clone(),
+            migrations: self.migrations.clone(),
+            rollback_on_failure: self.rollback_on_failure,
+            comparator: self.comparator.clone(),
+            source: self.source.clone(),
+            locks: self.locks.clone(),
+            lock_held: false,
+            max_version_gap: self.max_version_gap,
+            retry_policy: self.retry_policy,
+            #[cfg(feature = "event-log")]
+            event_log: self.event_log.clone(),
+            progress_callback: self.progress_callback.clone(),
+            transactions: self.transactions.clone(),
         }
+    }
+}
 
-        let migrated_versions = try!(self.migrated_versions());
-        let targets = self.migrations.iter()
-            // Rollback migrations from latest to oldest:
-            .rev()
-            // Rollback the current version, and all versions downwards until the specified version
-            // (exclusive):
-            .filter(|&(&v, _)| within_range(v, to, from))
-            // Rollback only the migrations that are actually already migrated (in the case that
-            // some intermediary migrations were never executed).
-            .filter(|&(v, _)| migrated_versions.contains(v));
-
-        for (&version, migration) in targets {
-            let description = migration.description();
-            info!("Reverting migration {:?}: {}", version, description);
-            if let Err(err) = self.adapter.revert_migration(migration) {
-                return Err(Error::Migration {
-                    version: version,
-                    description: description,
-                    direction: Direction::Down,
-                    error: err,
-                });
-            }
+// Measures how long a migration takes to execute. Without `std` there is no clock, so the
+// measured duration is always zero.
+struct Stopwatch {
+    #[cfg(feature = "std")]
+    start: std::time::Instant,
+}
+
+impl Stopwatch {
+    fn start() -> Stopwatch {
+        Stopwatch {
+            #[cfg(feature = "std")]
+            start: std::time::Instant::now(),
         }
+    }
 
-        Ok(())
+    fn elapsed(&self) -> Duration {
+        #[cfg(feature = "std")]
+        return self.start.elapsed();
+        #[cfg(not(feature = "std"))]
+        return Duration::ZERO;
     }
+}
 
-    /// Migrate to the specified version (inclusive).
-    pub fn up(&self, to: Option<Version>) -> Result<(), Error<T::Error>> {
-        let migrated_versions = try!(self.migrated_versions());
-        let targets = self.migrations.iter()
-            // Execute all versions upwards until the specified version (inclusive):
-            .filter(|&(&v, _)| within_range(v, None, to))
-            // Execute only the migrations that are actually not already migrated (in the case that
-            // some intermediary migrations were previously executed).
-            .filter(|&(v, _)| !migrated_versions.contains(v));
-
-        for (&version, migration) in targets {
-            let description = migration.description();
-            info!("Applying migration {:?}: {}", version, description);
-            if let Err(err) = self.adapter.apply_migration(migration) {
-                return Err(Error::Migration {
-                    version: version,
-                    description: description,
-                    direction: Direction::Up,
-                    error: err,
-                });
-            }
+// Describes a migration for `Migrator::explain_up` and `Migrator::explain_status`, prefixed with
+// its label and followed by its author and ticket, if any.
+fn explain_description<M: Migration + ?Sized>(migration: &M) -> String {
+    let mut description = match migration.label() {
+        Some(label) => format!("'{}': {}", label, migration.description()),
+        None => migration.description(),
+    };
+    let metadata: Vec<String> = migration.author().map(|author| format!("author: {}", author))
+        .into_iter()
+        .chain(migration.ticket().map(|ticket| format!("ticket: {}", ticket)))
+        .collect();
+    if !metadata.is_empty() {
+        description.push_str(&format!(" ({})", metadata.join(", ")));
+    }
+    description
+}
+
+// Formats migrations as a table of the form `| Version | Description | Status |`, padding every
+// column to its widest cell.
+fn format_table(rows: &[(Version, String, MigrationState)]) -> String {
+    let state_label = |state: &MigrationState| match *state {
+        MigrationState::Applied => "applied",
+        MigrationState::Pending => "pending",
+        MigrationState::Orphaned => "orphaned",
+    };
+    let cells: Vec<_> = rows.iter()
+        .map(|&(version, ref description, ref state)| {
+            (format!("{}", version), description.as_str(), state_label(state))
+        })
+        .collect();
+
+    let headers = ["Version", "Description", "Status"];
+    let mut widths = headers.map(|header| header.chars().count());
+    for &(ref version, description, state) in &cells {
+        for (width, cell) in widths.iter_mut().zip(&[version.as_str(), description, state]) {
+            *width = (*width).max(cell.chars().count());
         }
+    }
 
-        Ok(())
+    let mut table = String::new();
+    let mut push_row = |cells: [&str; 3]| {
+        for (cell, width) in cells.iter().zip(&widths) {
+            let padding = width - cell.chars().count();
+            table.push_str(&format!("| {}{} ", cell, " ".repeat(padding)));
+        }
+        table.push_str("|\n");
+    };
+    push_row(headers);
+    let rules: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+    push_row([&rules[0], &rules[1], &rules[2]]);
+    for &(ref version, description, state) in &cells {
+        push_row([version, description, state]);
     }
+    table
 }
 
 // Tests whether a `Version` is within a range defined by the exclusive `low` and the inclusive
@@ -263,26 +3535,65 @@ fn within_range(version: Version, low: Option<Version>, high: Option<Version>) -
 #[test]
 fn test_within_range() {
     // no lower or upper bound
-    assert!(within_range(0, None, None));
-    assert!(within_range(42, None, None));
-    assert!(within_range(100000, None, None));
+    assert!(within_range(Version(0), None, None));
+    assert!(within_range(Version(42), None, None));
+    assert!(within_range(Version(100000), None, None));
 
     // both lower and upper bounds
-    assert!(!within_range(1, Some(2), Some(5)));
-    assert!(!within_range(2, Some(2), Some(5)));
-    assert!(within_range(3, Some(2), Some(5)));
-    assert!(within_range(5, Some(2), Some(5)));
-    assert!(!within_range(6, Some(2), Some(5)));
+    assert!(!within_range(Version(1), Some(Version(2)), Some(Version(5))));
+    assert!(!within_range(Version(2), Some(Version(2)), Some(Version(5))));
+    assert!(within_range(Version(3), Some(Version(2)), Some(Version(5))));
+    assert!(within_range(Version(5), Some(Version(2)), Some(Version(5))));
+    assert!(!within_range(Version(6), Some(Version(2)), Some(Version(5))));
 
     // lower bound only
-    assert!(!within_range(0, Some(5), None));
-    assert!(!within_range(4, Some(5), None));
-    assert!(!within_range(5, Some(5), None));
-    assert!(within_range(6, Some(5), None));
-    assert!(within_range(60, Some(5), None));
+    assert!(!within_range(Version(0), Some(Version(5)), None));
+    assert!(!within_range(Version(4), Some(Version(5)), None));
+    assert!(!within_range(Version(5), Some(Version(5)), None));
+    assert!(within_range(Version(6), Some(Version(5)), None));
+    assert!(within_range(Version(60), Some(Version(5)), None));
 
     // upper bound only
-    assert!(within_range(0, None, Some(5)));
-    assert!(within_range(5, None, Some(5)));
-    assert!(!within_range(6, None, Some(5)));
+    assert!(within_range(Version(0), None, Some(Version(5))));
+    assert!(within_range(Version(5), None, Some(Version(5))));
+    assert!(!within_range(Version(6), None, Some(Version(5))));
+}
+
+#[test]
+fn test_validate_version_timestamp() {
+    assert_eq!(version_from_datetime(2023, 10, 15, 12, 0, 0), 20231015120000);
+    assert!(validate_version_timestamp(Version(20231015120000)));
+    assert!(validate_version_timestamp(Version(19700101000000)));
+    assert!(validate_version_timestamp(Version(29991231235959)));
+    assert!(validate_version_timestamp(Version(20240229000000)));
+
+    // out-of-range years
+    assert!(!validate_version_timestamp(Version(19691231235959)));
+    assert!(!validate_version_timestamp(Version(30000101000000)));
+
+    // invalid dates
+    assert!(!validate_version_timestamp(Version(20231301000000)));
+    assert!(!validate_version_timestamp(Version(20231000000000)));
+    assert!(!validate_version_timestamp(Version(20230431000000)));
+    assert!(!validate_version_timestamp(Version(20230229000000)));
+    assert!(!validate_version_timestamp(Version(21000229000000)));
+
+    // invalid times
+    assert!(!validate_version_timestamp(Version(20231015240000)));
+    assert!(!validate_version_timestamp(Version(20231015126000)));
+    assert!(!validate_version_timestamp(Version(20231015120060)));
+
+    // not timestamps at all
+    assert!(!validate_version_timestamp(Version(10)));
+    assert!(!validate_version_timestamp(Version(-20231015120000)));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_timestamp_from_unix() {
+    assert_eq!(timestamp_from_unix(0), 19700101000000);
+    assert_eq!(timestamp_from_unix(951782400), 20000229000000);
+    assert_eq!(timestamp_from_unix(1697371199), 20231015115959);
+    assert_eq!(timestamp_from_unix(4102444799), 20991231235959);
+    assert!(Version::timestamp_now().is_timestamp());
 }
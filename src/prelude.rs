@@ -0,0 +1,29 @@
+//! Re-exports the most commonly used types, for glob importing.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use schemamama::prelude::*;
+//!
+//! struct CreateUsers;
+//! migration!(CreateUsers, 10, "create users");
+//!
+//! # #[cfg(not(feature = "memory"))]
+//! # fn main() {}
+//! # #[cfg(feature = "memory")]
+//! # fn main() {
+//! let mut migrator = Migrator::new(InMemoryAdapter::new());
+//! migrator.register(Box::new(CreateUsers)).unwrap();
+//! if let Err(Error::Adapter { context: ErrorContext::Migration { version, .. }, .. }) =
+//!     migrator.up(None)
+//! {
+//!     panic!("failed to apply migration {}", version);
+//! }
+//! assert_eq!(migrator.current_version().unwrap(), Some(Version(10)));
+//! # }
+//! ```
+
+pub use super::{Adapter, Direction, Error, ErrorContext, Migration, Migrator, Version};
+#[cfg(feature = "memory")]
+pub use super::InMemoryAdapter;
+pub use crate::migration;
@@ -0,0 +1,217 @@
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use core::ops::{Index, RangeInclusive};
+use core::slice;
+
+use super::{Error, Migration, Version, VersionComparator};
+
+// The registered migrations of a migrator, keyed by version. Migrations are kept in a `Vec` sorted
+// by version, which is cheaper to iterate than a `BTreeMap` for typical numbers of migrations. The
+// methods mirror those of `BTreeMap`.
+pub struct Registry<M: ?Sized> {
+    entries: Vec<(Version, Box<M>)>,
+}
+
+impl<M: ?Sized> Registry<M> {
+    pub fn new() -> Registry<M> {
+        Registry { entries: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn contains_key(&self, version: &Version) -> bool {
+        self.position(*version).is_ok()
+    }
+
+    pub fn get(&self, version: &Version) -> Option<&M> {
+        self.position(*version).ok().map(|index| &*self.entries[index].1)
+    }
+
+    pub fn get_mut(&mut self, version: &Version) -> Option<&mut M> {
+        match self.position(*version) {
+            Ok(index) => Some(&mut *self.entries[index].1),
+            Err(_) => None,
+        }
+    }
+
+    // Inserts the migration at its sorted position, returning the migration it replaces, if any.
+    pub fn insert(&mut self, version: Version, migration: Box<M>) -> Option<Box<M>> {
+        match self.position(version) {
+            Ok(index) => Some(core::mem::replace(&mut self.entries[index].1, migration)),
+            Err(index) => {
+                self.entries.insert(index, (version, migration));
+                None
+            },
+        }
+    }
+
+    pub fn remove(&mut self, version: &Version) -> Option<Box<M>> {
+        match self.position(*version) {
+            Ok(index) => Some(self.entries.remove(index).1),
+            Err(_) => None,
+        }
+    }
+
+    pub fn retain<F>(&mut self, mut f: F) where F: FnMut(&Version, &mut Box<M>) -> bool {
+        self.entries.retain_mut(|(version, migration)| f(version, migration));
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &Version> + ExactSizeIterator {
+        self.entries.iter().map(|(version, _)| version)
+    }
+
+    pub fn iter(&self) -> Iter<'_, M> {
+        Iter { inner: self.entries.iter() }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, M> {
+        IterMut { inner: self.entries.iter_mut() }
+    }
+
+    // Iterates over the migrations with versions within the range. Unlike `BTreeMap::range`, an
+    // empty range does not panic.
+    pub fn range(&self, range: RangeInclusive<Version>) -> Iter<'_, M> {
+        let start = self.entries.partition_point(|&(version, _)| version < *range.start());
+        let end = self.entries.partition_point(|&(version, _)| version <= *range.end());
+        Iter { inner: self.entries[start..end.max(start)].iter() }
+    }
+
+    // Returns the versions sorted topologically by the migrations' dependencies. Among the
+    // migrations whose dependencies are satisfied, the first according to the comparator (or the
+    // lowest version, without one) always comes first.
+    pub fn ordered_versions<E>(&self, comparator: Option<&dyn VersionComparator>)
+        -> Result<Vec<Version>, Error<E>>
+        where M: Migration
+    {
+        let mut dependency_counts = BTreeMap::new();
+        let mut dependents = BTreeMap::new();
+        for (&version, migration) in self {
+            let dependencies = migration.dependencies();
+            for &dependency in &dependencies {
+                if !self.contains_key(&dependency) {
+                    return Err(Error::MissingDependency(dependency));
+                }
+                dependents.entry(dependency).or_insert_with(Vec::new).push(version);
+            }
+            dependency_counts.insert(version, dependencies.len());
+        }
+
+        let mut ready: BTreeSet<Version> = dependency_counts.iter()
+            .filter(|&(_, &count)| count == 0)
+            .map(|(&v, _)| v)
+            .collect();
+        let mut ordered = Vec::with_capacity(self.len());
+        let next = |ready: &mut BTreeSet<Version>| match comparator {
+            Some(comparator) => {
+                let version = ready.iter().copied().min_by(|a, b| comparator.compare(*a, *b))?;
+                ready.take(&version)
+            },
+            None => ready.pop_first(),
+        };
+        while let Some(version) = next(&mut ready) {
+            ordered.push(version);
+            for dependent in dependents.remove(&version).unwrap_or_default() {
+                let count = dependency_counts.get_mut(&dependent).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(dependent);
+                }
+            }
+        }
+
+        if ordered.len() == self.len() {
+            Ok(ordered)
+        } else {
+            Err(Error::DependencyCycle)
+        }
+    }
+
+    // Binary searches for the index of the version, or the index at which it would be inserted.
+    fn position(&self, version: Version) -> Result<usize, usize> {
+        self.entries.binary_search_by_key(&version, |&(version, _)| version)
+    }
+}
+
+impl<M: ?Sized> Clone for Registry<M> where Box<M>: Clone {
+    fn clone(&self) -> Registry<M> {
+        Registry { entries: self.entries.clone() }
+    }
+}
+
+impl<M: ?Sized> Index<&Version> for Registry<M> {
+    type Output = M;
+
+    fn index(&self, version: &Version) -> &M {
+        self.get(version).expect("no migration registered with the version")
+    }
+}
+
+impl<'a, M: ?Sized> IntoIterator for &'a Registry<M> {
+    type Item = (&'a Version, &'a M);
+    type IntoIter = Iter<'a, M>;
+
+    fn into_iter(self) -> Iter<'a, M> {
+        self.iter()
+    }
+}
+
+// An iterator over the entries of a `Registry`, in ascending order of version.
+pub struct Iter<'a, M: ?Sized> {
+    inner: slice::Iter<'a, (Version, Box<M>)>,
+}
+
+impl<'a, M: ?Sized> Iterator for Iter<'a, M> {
+    type Item = (&'a Version, &'a M);
+
+    fn next(&mut self) -> Option<(&'a Version, &'a M)> {
+        self.inner.next().map(|(version, migration)| (version, &**migration))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, M: ?Sized> DoubleEndedIterator for Iter<'a, M> {
+    fn next_back(&mut self) -> Option<(&'a Version, &'a M)> {
+        self.inner.next_back().map(|(version, migration)| (version, &**migration))
+    }
+}
+
+impl<'a, M: ?Sized> ExactSizeIterator for Iter<'a, M> {}
+
+// A mutable iterator over the entries of a `Registry`, in ascending order of version.
+pub struct IterMut<'a, M: ?Sized> {
+    inner: slice::IterMut<'a, (Version, Box<M>)>,
+}
+
+impl<'a, M: ?Sized> Iterator for IterMut<'a, M> {
+    type Item = (&'a Version, &'a mut M);
+
+    fn next(&mut self) -> Option<(&'a Version, &'a mut M)> {
+        self.inner.next().map(|(version, migration)| (&*version, &mut **migration))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, M: ?Sized> DoubleEndedIterator for IterMut<'a, M> {
+    fn next_back(&mut self) -> Option<(&'a Version, &'a mut M)> {
+        self.inner.next_back().map(|(version, migration)| (&*version, &mut **migration))
+    }
+}
+
+impl<'a, M: ?Sized> ExactSizeIterator for IterMut<'a, M> {}
@@ -0,0 +1,190 @@
+//! Utilities for testing code that drives a `Migrator`, without a database.
+
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::fmt;
+
+#[cfg(feature = "std")]
+use std::error::Error as StdError;
+
+use super::{Adapter, Direction, MarkableAdapter, Migration, Migrator, Version};
+
+/// An in-memory adapter that records every migration it is asked to run. Any migration type can
+/// be registered with it, as its `MigrationType` is `dyn Migration`.
+///
+/// ## Example
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate schemamama;
+/// use schemamama::{Direction, Migrator, Version};
+/// use schemamama::testing::MockAdapter;
+///
+/// struct CreateUsers;
+/// migration!(CreateUsers, 10, "create users");
+/// struct AddIndex;
+/// migration!(AddIndex, 20, "add index");
+///
+/// # fn main() {
+/// let mut adapter = MockAdapter::new();
+/// adapter.fail_on(Version(20), Direction::Up);
+///
+/// let mut migrator = Migrator::new(adapter);
+/// migrator.register(Box::new(CreateUsers)).unwrap();
+/// migrator.register(Box::new(AddIndex)).unwrap();
+///
+/// match migrator.up(None) {
+///     Err(ref err) if err.migration_version() == Some(Version(20)) => {},
+///     _ => panic!("expected the injected failure"),
+/// }
+/// let calls = [(Version(10), Direction::Up), (Version(20), Direction::Up)];
+/// assert_eq!(migrator.adapter().call_log(), &calls);
+/// assert!(migrator.adapter().is_applied(Version(10)));
+/// assert!(!migrator.adapter().is_applied(Version(20)));
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MockAdapter {
+    applied: BTreeSet<Version>,
+    calls: Vec<(Version, Direction)>,
+    failures: Vec<(Version, Direction)>,
+}
+
+impl MockAdapter {
+    /// Create an adapter with no applied migrations.
+    pub fn new() -> MockAdapter {
+        MockAdapter::default()
+    }
+
+    /// Makes every attempt to run the migration of the given version in the given direction fail
+    /// with a `MockError`. The attempt is still recorded in the call log.
+    pub fn fail_on(&mut self, version: Version, direction: Direction) -> &mut MockAdapter {
+        self.failures.push((version, direction));
+        self
+    }
+
+    /// Returns every call to `apply_migration` and `revert_migration`, in the order they were made,
+    /// including those that failed.
+    pub fn call_log(&self) -> &[(Version, Direction)] {
+        &self.calls
+    }
+
+    /// Returns the set of the versions of all of the currently applied migrations.
+    pub fn applied_versions(&self) -> &BTreeSet<Version> {
+        &self.applied
+    }
+
+    /// Returns true if the migration of the given version is currently applied.
+    pub fn is_applied(&self, version: Version) -> bool {
+        self.applied.contains(&version)
+    }
+
+    // Records the call, and returns the injected failure for it, if any.
+    fn call(&mut self, version: Version, direction: Direction) -> Result<(), MockError> {
+        let failing = self.failures.contains(&(version, direction));
+        self.calls.push((version, direction));
+        if failing {
+            Err(MockError { version, direction })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Adapter for MockAdapter {
+    type MigrationType = dyn Migration;
+    type Error = MockError;
+
+    fn current_schema_version(&mut self) -> Result<Option<Version>, MockError> {
+        Ok(self.applied.iter().next_back().copied())
+    }
+
+    fn migrated_versions(&mut self) -> Result<BTreeSet<Version>, MockError> {
+        Ok(self.applied.clone())
+    }
+
+    fn apply_migration(&mut self, migration: &dyn Migration) -> Result<(), MockError> {
+        self.call(migration.version(), Direction::Up)?;
+        self.applied.insert(migration.version());
+        Ok(())
+    }
+
+    fn revert_migration(&mut self, migration: &dyn Migration) -> Result<(), MockError> {
+        self.call(migration.version(), Direction::Down)?;
+        self.applied.remove(&migration.version());
+        Ok(())
+    }
+}
+
+impl MarkableAdapter for MockAdapter {
+    fn mark_applied(&mut self, version: Version) -> Result<(), MockError> {
+        self.applied.insert(version);
+        Ok(())
+    }
+}
+
+/// A migrator backed by a `MockAdapter`, with assertion helpers for tests.
+///
+/// ## Example
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate schemamama;
+/// use schemamama::Version;
+/// use schemamama::testing::TestMigrator;
+///
+/// struct CreateUsers;
+/// migration!(CreateUsers, 10, "create users");
+///
+/// # fn main() {
+/// let mut migrator = TestMigrator::new_empty();
+/// migrator.register(Box::new(CreateUsers)).unwrap();
+/// migrator.assert_not_applied(Version(10));
+/// migrator.up(None).unwrap();
+/// migrator.assert_applied(Version(10));
+/// # }
+/// ```
+pub type TestMigrator = Migrator<MockAdapter>;
+
+impl Migrator<MockAdapter> {
+    /// Create a migrator with a fresh `MockAdapter` and no registered migrations.
+    pub fn new_empty() -> TestMigrator {
+        Migrator::new(MockAdapter::new())
+    }
+
+    /// Panics if the migration of the given version is not applied.
+    #[track_caller]
+    pub fn assert_applied(&self, version: Version) {
+        let applied = self.adapter().applied_versions();
+        assert!(applied.contains(&version),
+                "expected migration {} to be applied, but the applied migrations are {:?}",
+                version, applied);
+    }
+
+    /// Panics if the migration of the given version is applied.
+    #[track_caller]
+    pub fn assert_not_applied(&self, version: Version) {
+        let applied = self.adapter().applied_versions();
+        assert!(!applied.contains(&version),
+                "expected migration {} not to be applied, but the applied migrations are {:?}",
+                version, applied);
+    }
+}
+
+/// The failure injected with `MockAdapter::fail_on`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockError {
+    /// The version of the migration that failed.
+    pub version: Version,
+    /// The direction in which the migration was run.
+    pub direction: Direction,
+}
+
+impl fmt::Display for MockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Injected failure of migration {} ({:?})", self.version, self.direction)
+    }
+}
+
+#[cfg(feature = "std")]
+impl StdError for MockError {}
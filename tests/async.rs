@@ -0,0 +1,165 @@
+#![cfg(feature = "async")]
+
+#[macro_use]
+extern crate schemamama;
+
+use schemamama::{AsyncAdapter, AsyncMigrator, Direction, Error, Migration};
+use schemamama::{RegistrationError, Version};
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+// The dummy adapter never actually suspends, so a single poll is enough to drive each future.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+struct DummyAdapter {
+    versions: BTreeSet<Version>,
+    calls: Vec<(Version, Direction)>,
+}
+
+impl DummyAdapter {
+    pub fn new() -> DummyAdapter {
+        DummyAdapter { versions: BTreeSet::new(), calls: Vec::new() }
+    }
+
+    pub fn is_migrated(&self, version: Version) -> bool {
+        self.versions.contains(&version)
+    }
+}
+
+impl AsyncAdapter for DummyAdapter {
+    type MigrationType = dyn Migration + Send + Sync;
+    type Error = ();
+
+    async fn current_schema_version(&mut self) -> Result<Option<Version>, ()> {
+        Ok(self.versions.iter().last().copied())
+    }
+
+    async fn migrated_versions(&mut self) -> Result<BTreeSet<Version>, ()> {
+        Ok(self.versions.iter().cloned().collect())
+    }
+
+    async fn apply_migration(&mut self, migration: &Self::MigrationType) -> Result<(), ()> {
+        self.versions.insert(migration.version());
+        self.calls.push((migration.version(), Direction::Up));
+        Ok(())
+    }
+
+    async fn revert_migration(&mut self, migration: &Self::MigrationType) -> Result<(), ()> {
+        self.versions.remove(&migration.version());
+        self.calls.push((migration.version(), Direction::Down));
+        Ok(())
+    }
+}
+
+struct FirstMigration;
+migration!(FirstMigration, 10, "first migration");
+struct SecondMigration;
+migration!(SecondMigration, 20, "second migration");
+
+#[test]
+fn test_registration() {
+    let mut migrator = AsyncMigrator::new(DummyAdapter::new());
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    assert_eq!(migrator.register(Box::new(FirstMigration)),
+               Err(RegistrationError::DuplicateVersion(Version(10))));
+    assert_eq!(migrator.first_version(), Some(Version(10)));
+    assert_eq!(migrator.last_version(), Some(Version(20)));
+    assert_eq!(migrator.registered_versions().len(), 2);
+}
+
+#[test]
+fn test_migrate() {
+    let mut migrator = AsyncMigrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    assert_eq!(block_on(migrator.current_version()).unwrap(), None);
+    assert_eq!(block_on(migrator.up(Some(Version(20)))).unwrap(), vec![Version(10), Version(20)]);
+    assert_eq!(block_on(migrator.current_version()).unwrap(), Some(Version(20)));
+    assert_eq!(block_on(migrator.down(Some(Version(10)))).unwrap(), vec![Version(20)]);
+    assert_eq!(block_on(migrator.current_version()).unwrap(), Some(Version(10)));
+    assert_eq!(block_on(migrator.down(None)).unwrap(), vec![Version(10)]);
+    assert_eq!(block_on(migrator.current_version()).unwrap(), None);
+    assert!(block_on(migrator.down(None)).unwrap().is_empty());
+    assert_eq!(block_on(migrator.up(None)).unwrap(), vec![Version(10), Version(20)]);
+    assert_eq!(block_on(migrator.current_version()).unwrap(), Some(Version(20)));
+    assert!(block_on(migrator.up(None)).unwrap().is_empty());
+}
+
+#[test]
+fn test_schema_version() {
+    let mut migrator = AsyncMigrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    assert_eq!(block_on(migrator.schema_version()).unwrap(), None);
+    block_on(migrator.up(Some(Version(10)))).unwrap();
+    assert_eq!(block_on(migrator.schema_version()).unwrap(), Some(Version(10)));
+}
+
+#[test]
+fn test_retroactive_migrations() {
+    let mut migrator = AsyncMigrator::new(DummyAdapter::new());
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    block_on(migrator.up(Some(Version(20)))).unwrap();
+    assert!(!migrator.adapter().is_migrated(Version(10)));
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    block_on(migrator.up(Some(Version(20)))).unwrap();
+    assert!(migrator.adapter().is_migrated(Version(10)));
+    assert!(migrator.adapter().is_migrated(Version(20)));
+}
+
+struct DependentMigration;
+impl Migration for DependentMigration {
+    fn version(&self) -> Version { Version(5) }
+    fn description(&self) -> String { "dependent migration".into() }
+    fn dependencies(&self) -> Vec<Version> { vec![Version(20)] }
+}
+
+#[test]
+fn test_dependencies() {
+    let mut migrator = AsyncMigrator::new(DummyAdapter::new());
+    migrator.register(Box::new(DependentMigration)).unwrap();
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    block_on(migrator.up(None)).unwrap();
+    block_on(migrator.down(None)).unwrap();
+    assert_eq!(migrator.adapter().calls, vec![
+        (Version(10), Direction::Up),
+        (Version(20), Direction::Up),
+        (Version(5), Direction::Up),
+        (Version(5), Direction::Down),
+        (Version(20), Direction::Down),
+        (Version(10), Direction::Down),
+    ]);
+}
+
+// Compiles only if the migrator's futures are `Send` for any adapter, so that they can be spawned
+// on a multi-threaded runtime.
+fn assert_send<F: Future + Send>(future: F) -> F {
+    future
+}
+
+async fn migrate<T: AsyncAdapter>(migrator: &mut AsyncMigrator<T>) -> Result<(), Error<T::Error>> {
+    assert_send(migrator.up(None)).await?;
+    assert_send(migrator.down(None)).await?;
+    assert_send(migrator.current_version()).await.map(|_| ())
+}
+
+#[test]
+fn test_send() {
+    let mut migrator = AsyncMigrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    block_on(assert_send(migrate(&mut migrator))).unwrap();
+    assert_eq!(migrator.adapter().calls,
+               vec![(Version(10), Direction::Up), (Version(10), Direction::Down)]);
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "event-log")]
+
+#[macro_use]
+extern crate schemamama;
+
+use schemamama::{Adapter, Direction, Migration, Migrator, Version};
+use std::collections::BTreeSet;
+
+struct DummyAdapter {
+    versions: BTreeSet<Version>,
+    failing_applies: BTreeSet<Version>,
+}
+
+impl Adapter for DummyAdapter {
+    type MigrationType = dyn Migration;
+    type Error = ();
+
+    fn current_version(&mut self) -> Result<Option<Version>, ()> {
+        Ok(self.versions.iter().last().copied())
+    }
+
+    fn migrated_versions(&mut self) -> Result<BTreeSet<Version>, ()> {
+        Ok(self.versions.clone())
+    }
+
+    fn apply_migration(&mut self, migration: &dyn Migration) -> Result<(), ()> {
+        if self.failing_applies.contains(&migration.version()) {
+            return Err(());
+        }
+        self.versions.insert(migration.version());
+        Ok(())
+    }
+
+    fn revert_migration(&mut self, migration: &dyn Migration) -> Result<(), ()> {
+        self.versions.remove(&migration.version());
+        Ok(())
+    }
+}
+
+struct FirstMigration;
+migration!(FirstMigration, 10, "first migration");
+struct SecondMigration;
+migration!(SecondMigration, 20, "second migration");
+
+#[test]
+fn test_event_log() {
+    let adapter = DummyAdapter {
+        versions: BTreeSet::new(),
+        failing_applies: vec![Version(20)].into_iter().collect(),
+    };
+    let mut migrator = Migrator::new(adapter);
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    assert!(migrator.up(None).is_err());
+    migrator.down(None).unwrap();
+
+    let events: Vec<_> = migrator.event_log().iter()
+        .map(|event| (event.version, event.description.as_str(), event.direction, event.success))
+        .collect();
+    assert_eq!(events, vec![(Version(10), "first migration", Direction::Up, true),
+                            (Version(20), "second migration", Direction::Up, false),
+                            (Version(10), "first migration", Direction::Down, true)]);
+    let log = migrator.event_log();
+    assert!(log[0].timestamp <= log[1].timestamp && log[1].timestamp <= log[2].timestamp);
+}
@@ -0,0 +1,71 @@
+#![cfg(all(feature = "kv-logging", feature = "memory", not(feature = "tracing")))]
+
+#[macro_use]
+extern crate schemamama;
+
+use log::kv::{Error as KvError, Key, Value, VisitSource};
+use log::{Log, Metadata, Record};
+use schemamama::{InMemoryAdapter, Migrator};
+use std::sync::Mutex;
+
+// A logged message along with its key-value pairs.
+type Captured = (String, Vec<(String, String)>);
+
+struct CapturingLogger {
+    records: Mutex<Vec<Captured>>,
+}
+
+struct Pairs(Vec<(String, String)>);
+
+impl<'kvs> VisitSource<'kvs> for Pairs {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), KvError> {
+        self.0.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+}
+
+impl Log for CapturingLogger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let mut pairs = Pairs(Vec::new());
+        record.key_values().visit(&mut pairs).unwrap();
+        self.records.lock().unwrap().push((record.args().to_string(), pairs.0));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CapturingLogger = CapturingLogger { records: Mutex::new(Vec::new()) };
+
+struct FirstMigration;
+migration!(FirstMigration, 10, "first migration");
+struct SecondMigration;
+migration!(SecondMigration, 20, "second migration");
+
+#[test]
+fn test_key_value_pairs() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Info);
+
+    let mut migrator = Migrator::new(InMemoryAdapter::new()).loaded_from("schema.rs");
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.up(None).unwrap();
+
+    let pairs = vec![("source".to_string(), "schema.rs".to_string()),
+                     ("version".to_string(), "10".to_string()),
+                     ("description".to_string(), "first migration".to_string())];
+    assert_eq!(*LOGGER.records.lock().unwrap(), vec![("Applying migration".to_string(), pairs)]);
+
+    // Without a source, no `source` pair is logged:
+    LOGGER.records.lock().unwrap().clear();
+    let mut migrator = Migrator::new(InMemoryAdapter::new());
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(None).unwrap();
+
+    let pairs = vec![("version".to_string(), "20".to_string()),
+                     ("description".to_string(), "second migration".to_string())];
+    assert_eq!(*LOGGER.records.lock().unwrap(), vec![("Applying migration".to_string(), pairs)]);
+}
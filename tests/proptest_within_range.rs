@@ -0,0 +1,32 @@
+#![cfg(feature = "proptest")]
+
+use proptest::prelude::*;
+use schemamama::Version;
+use schemamama::__private::within_range;
+
+// Every version with both a predecessor and a successor:
+fn version() -> impl Strategy<Value = Version> {
+    (i64::MIN + 1..i64::MAX).prop_map(Version)
+}
+
+proptest! {
+    #[test]
+    fn test_upper_bound_is_inclusive(v in version()) {
+        prop_assert!(within_range(v, Some(Version(v.0 - 1)), Some(v)));
+        prop_assert!(within_range(v, None, Some(v)));
+    }
+
+    #[test]
+    fn test_lower_bound_is_exclusive(v in version()) {
+        prop_assert!(!within_range(v, Some(v), Some(Version(v.0 + 1))));
+        prop_assert!(!within_range(v, Some(v), None));
+    }
+
+    #[test]
+    fn test_bounds(v in version(), low in version(), high in version()) {
+        prop_assert_eq!(within_range(v, Some(low), Some(high)), low < v && v <= high);
+        prop_assert_eq!(within_range(v, Some(low), None), low < v);
+        prop_assert_eq!(within_range(v, None, Some(high)), v <= high);
+        prop_assert!(within_range(v, None, None));
+    }
+}
@@ -0,0 +1,119 @@
+#![cfg(feature = "serde")]
+
+#[macro_use]
+extern crate schemamama;
+extern crate serde_json;
+
+use schemamama::{Adapter, Direction, Error, ErrorContext, Migration, Migrator, Version};
+use std::collections::BTreeSet;
+
+struct AppliedAdapter {
+    versions: BTreeSet<Version>,
+}
+
+impl Adapter for AppliedAdapter {
+    type MigrationType = dyn Migration;
+    type Error = ();
+
+    fn current_schema_version(&mut self) -> Result<Option<Version>, ()> {
+        Ok(self.versions.iter().last().copied())
+    }
+
+    fn migrated_versions(&mut self) -> Result<BTreeSet<Version>, ()> {
+        Ok(self.versions.clone())
+    }
+
+    fn apply_migration(&mut self, _: &dyn Migration) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn revert_migration(&mut self, _: &dyn Migration) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+struct FirstMigration;
+migration!(FirstMigration, 10, "first migration");
+struct SecondMigration;
+migration!(SecondMigration, 20, "second migration");
+
+#[test]
+fn test_direction_round_trip() {
+    let json = serde_json::to_string(&Direction::Up).unwrap();
+    assert_eq!(json, "\"Up\"");
+    assert_eq!(serde_json::from_str::<Direction>(&json).unwrap(), Direction::Up);
+}
+
+#[test]
+fn test_error_round_trip() {
+    let context = ErrorContext::Migration {
+        version: Version(10),
+        description: "first migration".into(),
+        label: None,
+        direction: Direction::Down,
+    };
+    let error: Error<String> = Error::Adapter {
+        context: context.clone(),
+        error: "relation does not exist".into(),
+    };
+    let json = serde_json::to_string(&error).unwrap();
+    match serde_json::from_str::<Error<String>>(&json).unwrap() {
+        Error::Adapter { context: actual, error } => {
+            assert_eq!(actual, context);
+            assert_eq!(error, "relation does not exist");
+        },
+        _ => panic!("expected `Error::Adapter`"),
+    }
+
+    let error: Error<String> = Error::adapter("connection refused".into());
+    let json = serde_json::to_string(&error).unwrap();
+    match serde_json::from_str::<Error<String>>(&json).unwrap() {
+        Error::Adapter { context: ErrorContext::Adapter, error } => {
+            assert_eq!(error, "connection refused");
+        },
+        _ => panic!("expected `Error::Adapter`"),
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_migration_record_round_trip() {
+    use schemamama::MigrationRecord;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let record = MigrationRecord {
+        version: Version(20),
+        description: "second migration".into(),
+        applied_at: UNIX_EPOCH + Duration::from_secs(1_500_000_000),
+        duration: Duration::from_millis(250),
+        author: Some("alice".into()),
+        ticket: None,
+    };
+    let json = serde_json::to_string(&record).unwrap();
+    let decoded: MigrationRecord = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.version, record.version);
+    assert_eq!(decoded.description, record.description);
+    assert_eq!(decoded.applied_at, record.applied_at);
+    assert_eq!(decoded.duration, record.duration);
+    assert_eq!(decoded.author, record.author);
+    assert_eq!(decoded.ticket, None);
+}
+
+#[test]
+fn test_registry_json_round_trip() {
+    let mut migrator = Migrator::new(AppliedAdapter { versions: BTreeSet::new() });
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    let json = migrator.registry_to_json();
+    assert_eq!(json, "[{\"version\":10,\"description\":\"first migration\"},\
+                      {\"version\":20,\"description\":\"second migration\"}]");
+
+    let adapter = AppliedAdapter { versions: vec![Version(10)].into_iter().collect() };
+    let mut restored = Migrator::registry_from_json(adapter, &json).unwrap();
+    assert_eq!(restored.registered_versions(), migrator.registered_versions());
+    assert_eq!(restored.find_migration(Version(20)).unwrap().description(), "second migration");
+    assert_eq!(restored.pending_versions().unwrap(), vec![Version(20)].into_iter().collect());
+
+    assert!(Migrator::registry_from_json(AppliedAdapter { versions: BTreeSet::new() },
+                                         "{\"version\":10}").is_err());
+}
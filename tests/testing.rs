@@ -0,0 +1,74 @@
+#![cfg(feature = "testing")]
+
+#[macro_use]
+extern crate schemamama;
+
+use schemamama::testing::{MockAdapter, MockError, TestMigrator};
+use schemamama::{Direction, Error, ErrorContext, Migrator, Version};
+
+struct FirstMigration;
+migration!(FirstMigration, 10, "first migration");
+struct SecondMigration;
+migration!(SecondMigration, 20, "second migration");
+
+fn migrator(adapter: MockAdapter) -> Migrator<MockAdapter> {
+    let mut migrator = Migrator::new(adapter);
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator
+}
+
+#[test]
+fn test_call_log() {
+    let mut migrator = migrator(MockAdapter::new());
+    migrator.up(None).unwrap();
+    migrator.down(Some(Version(10))).unwrap();
+    assert_eq!(migrator.adapter().call_log(),
+               &[(Version(10), Direction::Up), (Version(20), Direction::Up),
+                 (Version(20), Direction::Down)]);
+    assert_eq!(migrator.adapter().applied_versions(), &vec![Version(10)].into_iter().collect());
+}
+
+#[test]
+fn test_fail_on() {
+    let mut adapter = MockAdapter::new();
+    adapter.fail_on(Version(10), Direction::Down);
+    let mut migrator = migrator(adapter);
+    migrator.up(None).unwrap();
+
+    match migrator.down(None) {
+        Err(Error::Adapter {
+            context: ErrorContext::Migration {
+                version: Version(10),
+                direction: Direction::Down,
+                ..
+            },
+            error,
+        }) => {
+            assert_eq!(error, MockError { version: Version(10), direction: Direction::Down });
+        },
+        _ => panic!("expected `Error::Adapter`"),
+    }
+    assert!(migrator.adapter().is_applied(Version(10)));
+    assert!(!migrator.adapter().is_applied(Version(20)));
+    assert_eq!(migrator.adapter().call_log().last(), Some(&(Version(10), Direction::Down)));
+}
+
+#[test]
+fn test_test_migrator() {
+    let mut migrator = TestMigrator::new_empty();
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(Some(Version(10))).unwrap();
+    migrator.assert_applied(Version(10));
+    migrator.assert_not_applied(Version(20));
+}
+
+#[test]
+#[should_panic(expected = "expected migration 20 to be applied")]
+fn test_assert_applied_panics() {
+    let mut migrator = TestMigrator::new_empty();
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.up(None).unwrap();
+    migrator.assert_applied(Version(20));
+}
@@ -1,49 +1,206 @@
 #[macro_use]
 extern crate schemamama;
 
-use schemamama::{Adapter, Migration, Migrator, Version};
-use std::cell::RefCell;
-use std::collections::BTreeSet;
+use schemamama::{Adapter, BackoffStrategy, ConstVersion, Direction, Error, ErrorContext};
+use schemamama::{IntegrityReport, LockableAdapter, MarkableAdapter, Migration, MigrationCommand};
+use schemamama::{MigrationDiff, MigrationPhase, MigrationState};
+use schemamama::{Migrator, ProgressEvent, RegistrationError, RetryPolicy, ReversibleMigration};
+use schemamama::{SortOrder, SquashError, TransactionalAdapter, Version, VersionComparator};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+#[derive(Debug, Default)]
 struct DummyAdapter {
-    versions: RefCell<BTreeSet<Version>>
+    versions: BTreeSet<Version>,
+    applied_order: Vec<Version>,
+    checksums: BTreeMap<Version, u64>,
+    failing_applies: BTreeSet<Version>,
+    failing_reverts: BTreeSet<Version>,
+    flaky_applies: BTreeMap<Version, u32>,
+    transaction_log: Vec<&'static str>,
+    failing_rollbacks: bool,
+    batch_log: Vec<&'static str>,
+    changelog: Vec<String>,
+    lock_log: Vec<&'static str>,
+    lock_contended: bool,
+    up_only: bool,
+    batching: bool,
+    batches: Vec<(Direction, Vec<Version>)>,
 }
 
 impl DummyAdapter {
     pub fn new() -> DummyAdapter {
-        DummyAdapter { versions: RefCell::new(BTreeSet::new()) }
+        DummyAdapter {
+            versions: BTreeSet::new(),
+            applied_order: Vec::new(),
+            checksums: BTreeMap::new(),
+            failing_applies: BTreeSet::new(),
+            failing_reverts: BTreeSet::new(),
+            flaky_applies: BTreeMap::new(),
+            transaction_log: Vec::new(),
+            failing_rollbacks: false,
+            batch_log: Vec::new(),
+            changelog: Vec::new(),
+            lock_log: Vec::new(),
+            lock_contended: false,
+            up_only: false,
+            batching: false,
+            batches: Vec::new(),
+        }
     }
 
     pub fn is_migrated(&self, version: Version) -> bool {
-        self.versions.borrow().contains(&version)
+        self.versions.contains(&version)
     }
 }
 
 impl Adapter for DummyAdapter {
-    type MigrationType = Migration;
+    type MigrationType = dyn Migration;
     type Error = ();
 
-    fn current_version(&self) -> Result<Option<Version>, ()> {
-        Ok(self.versions.borrow().iter().last().map(|v| *v))
+    fn current_version(&mut self) -> Result<Option<Version>, ()> {
+        Ok(self.versions.iter().last().copied())
     }
 
-    fn migrated_versions(&self) -> Result<BTreeSet<Version>, ()> {
-        Ok(self.versions.borrow().iter().cloned().collect())
+    fn migrated_versions(&mut self) -> Result<BTreeSet<Version>, ()> {
+        Ok(self.versions.iter().cloned().collect())
     }
 
-    fn apply_migration(&self, migration: &Migration) -> Result<(), ()> {
-        self.versions.borrow_mut().insert(migration.version());
+    fn apply_migration(&mut self, migration: &dyn Migration) -> Result<(), ()> {
+        if self.failing_applies.contains(&migration.version()) {
+            return Err(());
+        }
+        if let Some(failures) = self.flaky_applies.get_mut(&migration.version()) {
+            if *failures > 0 {
+                *failures -= 1;
+                return Err(());
+            }
+        }
+        self.versions.insert(migration.version());
+        self.applied_order.push(migration.version());
         Ok(())
     }
 
-    fn revert_migration(&self, migration: &Migration) -> Result<(), ()> {
-        self.versions.borrow_mut().remove(&migration.version());
+    fn revert_migration(&mut self, migration: &dyn Migration) -> Result<(), ()> {
+        if self.failing_reverts.contains(&migration.version()) {
+            return Err(());
+        }
+        self.versions.remove(&migration.version());
+        self.applied_order.retain(|&version| version != migration.version());
+        Ok(())
+    }
+
+    fn batch_apply(&mut self, migrations: &[&Self::MigrationType]) -> Result<(), ()> {
+        self.batches.push((Direction::Up, migrations.iter().map(|m| m.version()).collect()));
+        for migration in migrations {
+            self.apply_migration(*migration)?;
+        }
+        Ok(())
+    }
+
+    fn batch_revert(&mut self, migrations: &[&Self::MigrationType]) -> Result<(), ()> {
+        self.batches.push((Direction::Down, migrations.iter().map(|m| m.version()).collect()));
+        for migration in migrations {
+            self.revert_migration(*migration)?;
+        }
+        Ok(())
+    }
+
+    fn supports_batch(&self) -> bool {
+        self.batching
+    }
+
+    fn versions_applied_in_order(&mut self) -> Result<Vec<Version>, ()> {
+        Ok(self.applied_order.clone())
+    }
+
+    fn record_checksum(&mut self, version: Version, checksum: u64) -> Result<(), ()> {
+        self.checksums.insert(version, checksum);
+        Ok(())
+    }
+
+    fn verify_checksum(&mut self, version: Version) -> Result<Option<u64>, ()> {
+        Ok(self.checksums.get(&version).cloned())
+    }
+
+    fn before_migration_batch(&mut self) -> Result<(), ()> {
+        self.batch_log.push("before");
+        Ok(())
+    }
+
+    fn after_migration_batch(&mut self, success: bool) -> Result<(), ()> {
+        self.batch_log.push(if success { "success" } else { "failure" });
+        Ok(())
+    }
+
+    fn supports_down_migrations(&self) -> bool {
+        !self.up_only
+    }
+
+    fn on_migration_applied(&mut self, version: Version, description: &str, _: Duration)
+        -> Result<(), ()>
+    {
+        self.changelog.push(format!("applied {}: {}", version, description));
+        Ok(())
+    }
+
+    fn on_migration_reverted(&mut self, version: Version, description: &str, _: Duration)
+        -> Result<(), ()>
+    {
+        self.changelog.push(format!("reverted {}: {}", version, description));
+        Ok(())
+    }
+}
+
+impl MarkableAdapter for DummyAdapter {
+    fn mark_applied(&mut self, version: Version) -> Result<(), ()> {
+        self.versions.insert(version);
+        self.applied_order.push(version);
+        Ok(())
+    }
+}
+
+impl LockableAdapter for DummyAdapter {
+    fn acquire_lock(&mut self) -> Result<(), ()> {
+        if self.lock_contended {
+            return Err(());
+        }
+        self.lock_log.push("acquire");
+        Ok(())
+    }
+
+    fn release_lock(&mut self) -> Result<(), ()> {
+        self.lock_log.push("release");
+        Ok(())
+    }
+}
+
+impl TransactionalAdapter for DummyAdapter {
+    fn begin(&mut self) -> Result<(), ()> {
+        self.transaction_log.push("begin");
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<(), ()> {
+        self.transaction_log.push("commit");
+        Ok(())
+    }
+
+    fn rollback(&mut self) -> Result<(), ()> {
+        self.transaction_log.push("rollback");
+        if self.failing_rollbacks {
+            return Err(());
+        }
         Ok(())
     }
 }
 
+#[derive(Clone)]
 struct FirstMigration;
 migration!(FirstMigration, 10, "first migration");
+#[derive(Clone)]
 struct SecondMigration;
 migration!(SecondMigration, 20, "second migration");
 
@@ -52,51 +209,1756 @@ fn test_registration() {
     let mut migrator = Migrator::new(DummyAdapter::new());
     assert_eq!(migrator.first_version(), None);
     assert_eq!(migrator.last_version(), None);
-    migrator.register(Box::new(SecondMigration));
-    migrator.register(Box::new(FirstMigration));
-    assert_eq!(migrator.first_version(), Some(10));
-    assert_eq!(migrator.last_version(), Some(20));
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    assert_eq!(migrator.first_version(), Some(Version(10)));
+    assert_eq!(migrator.last_version(), Some(Version(20)));
     let mut versions = BTreeSet::new();
-    versions.insert(10);
-    versions.insert(20);
+    versions.insert(Version(10));
+    versions.insert(Version(20));
     assert_eq!(migrator.registered_versions(), versions);
 }
 
+#[test]
+fn test_duplicate_registration() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    assert_eq!(migrator.register(Box::new(FirstMigration)),
+               Err(RegistrationError::DuplicateVersion(Version(10))));
+    migrator.register_or_warn(Box::new(FirstMigration));
+    assert_eq!(migrator.registered_versions().len(), 1);
+}
+
 #[test]
 fn test_version_registered() {
     let mut migrator = Migrator::new(DummyAdapter::new());
-    assert_eq!(migrator.version_registered(10), false);
-    migrator.register(Box::new(FirstMigration));
-    assert_eq!(migrator.version_registered(10), true);
+    assert!(!migrator.version_registered(Version(10)));
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    assert!(migrator.version_registered(Version(10)));
 }
 
 #[test]
 fn test_migrate() {
     let mut migrator = Migrator::new(DummyAdapter::new());
-    migrator.register(Box::new(FirstMigration));
-    migrator.register(Box::new(SecondMigration));
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    assert_eq!(migrator.current_version().unwrap(), None);
+    assert_eq!(migrator.up(Some(Version(20))).unwrap(), vec![Version(10), Version(20)]);
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(20)));
+    assert_eq!(migrator.down(Some(Version(10))).unwrap(), vec![Version(20)]);
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(10)));
+    assert_eq!(migrator.down(None).unwrap(), vec![Version(10)]);
+    assert_eq!(migrator.current_version().unwrap(), None);
+    assert!(migrator.down(None).unwrap().is_empty());
+    assert_eq!(migrator.up(None).unwrap(), vec![Version(10), Version(20)]);
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(20)));
+    assert!(migrator.up(None).unwrap().is_empty());
+}
+
+#[test]
+fn test_schema_version() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    assert_eq!(migrator.schema_version().unwrap(), None);
+    migrator.up(Some(Version(10))).unwrap();
+    assert_eq!(migrator.schema_version().unwrap(), Some(Version(10)));
+}
+
+#[test]
+fn test_retroactive_migrations() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(Some(Version(20))).unwrap();
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(20)));
+    assert!(migrator.adapter().is_migrated(Version(20)));
+    assert!(!migrator.adapter().is_migrated(Version(10)));
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.up(Some(Version(20))).unwrap();
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(20)));
+    assert!(migrator.adapter().is_migrated(Version(20)));
+    assert!(migrator.adapter().is_migrated(Version(10)));
+}
+
+#[test]
+fn test_migrate_to() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.migrate_to(Some(Version(20))).unwrap();
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(20)));
+    migrator.migrate_to(Some(Version(10))).unwrap();
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(10)));
+    migrator.migrate_to(None).unwrap();
+    assert_eq!(migrator.current_version().unwrap(), None);
+    match migrator.migrate_to(Some(Version(15))) {
+        Err(Error::VersionNotFound(Version(15))) => {},
+        _ => panic!("expected `Error::VersionNotFound(15)`"),
+    }
+    assert_eq!(migrator.current_version().unwrap(), None);
+}
+
+#[test]
+fn test_migrate_to_retroactive() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(DataMigration)).unwrap();
+    migrator.up(None).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    assert_eq!(migrator.migrate_to(Some(Version(20))).unwrap(), vec![Version(25), Version(20)]);
+    assert_eq!(migrator.migrated_versions().unwrap(),
+               vec![Version(10), Version(20)].into_iter().collect());
+
+    // An applied version that is no longer registered does not prevent migrating upwards:
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.adapter_mut().versions.insert(Version(50));
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    assert_eq!(migrator.migrate_to(Some(Version(20))).unwrap(), vec![Version(10), Version(20)]);
+    assert!(migrator.adapter().is_migrated(Version(50)));
+}
+
+#[test]
+fn test_plan() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    let plan: Vec<Version> = migrator.plan_up(None).unwrap().iter().map(|m| m.version()).collect();
+    assert_eq!(plan, vec![Version(10), Version(20)]);
     assert_eq!(migrator.current_version().unwrap(), None);
-    migrator.up(Some(20)).unwrap();
-    assert_eq!(migrator.current_version().unwrap(), Some(20));
-    migrator.down(Some(10)).unwrap();
-    assert_eq!(migrator.current_version().unwrap(), Some(10));
+    migrator.up(Some(Version(10))).unwrap();
+    let plan: Vec<Version> = migrator.plan_up(None).unwrap().iter().map(|m| m.version()).collect();
+    assert_eq!(plan, vec![Version(20)]);
+    migrator.up(None).unwrap();
+    let plan: Vec<Version> =
+        migrator.plan_down(None).unwrap().iter().map(|m| m.version()).collect();
+    assert_eq!(plan, vec![Version(20), Version(10)]);
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(20)));
+}
+
+struct DependentMigration;
+impl Migration for DependentMigration {
+    fn version(&self) -> Version { Version(5) }
+    fn description(&self) -> String { "dependent migration".into() }
+    fn dependencies(&self) -> Vec<Version> { vec![Version(20)] }
+}
+
+struct CyclicMigration;
+impl Migration for CyclicMigration {
+    fn version(&self) -> Version { Version(30) }
+    fn description(&self) -> String { "cyclic migration".into() }
+    fn dependencies(&self) -> Vec<Version> { vec![Version(30)] }
+}
+
+#[test]
+fn test_dependencies() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(DependentMigration)).unwrap();
+    match migrator.validate() {
+        Err(Error::MissingDependency(Version(20))) => {},
+        _ => panic!("expected `Error::MissingDependency(20)`"),
+    }
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.validate().unwrap();
+    let plan: Vec<Version> = migrator.plan_up(None).unwrap().iter().map(|m| m.version()).collect();
+    assert_eq!(plan, vec![Version(10), Version(20), Version(5)]);
+    match migrator.up(Some(Version(10))) {
+        Err(Error::MissingDependency(Version(20))) => {},
+        _ => panic!("expected `Error::MissingDependency(20)`"),
+    }
+    migrator.up(None).unwrap();
+    assert!(migrator.adapter().is_migrated(Version(5)));
+    let plan: Vec<Version> =
+        migrator.plan_down(None).unwrap().iter().map(|m| m.version()).collect();
+    assert_eq!(plan, vec![Version(5), Version(20), Version(10)]);
+    migrator.register(Box::new(CyclicMigration)).unwrap();
+    match migrator.validate() {
+        Err(Error::DependencyCycle) => {},
+        _ => panic!("expected `Error::DependencyCycle`"),
+    }
+}
+
+struct ChecksummedMigration;
+impl Migration for ChecksummedMigration {
+    fn version(&self) -> Version { Version(40) }
+    fn description(&self) -> String { "checksummed migration".into() }
+    fn checksum(&self) -> Option<u64> { Some(0xc0ffee) }
+}
+
+#[test]
+fn test_checksums() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(ChecksummedMigration)).unwrap();
+    migrator.up(None).unwrap();
+    assert_eq!(migrator.adapter().checksums.get(&Version(40)), Some(&0xc0ffee));
+    migrator.verify().unwrap();
+    // Simulate the migration having been altered since it was applied:
+    migrator.adapter_mut().checksums.insert(Version(40), 0xdecaf);
+    match migrator.verify() {
+        Err(Error::ChecksumMismatch { version: Version(40), expected: 0xdecaf,
+                                      actual: 0xc0ffee }) => {},
+        _ => panic!("expected `Error::ChecksumMismatch`"),
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_history() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.up(None).unwrap();
+    // `DummyAdapter` relies on the default implementation, which stores no records:
+    assert!(migrator.history().unwrap().is_empty());
+}
+
+struct AuthMigration;
+impl Migration for AuthMigration {
+    fn version(&self) -> Version { Version(15) }
+    fn description(&self) -> String { "auth migration".into() }
+    fn namespace(&self) -> &'static str { "auth" }
+}
+
+#[test]
+fn test_namespaces() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up_namespace("auth", None).unwrap();
+    assert!(migrator.adapter().is_migrated(Version(15)));
+    assert!(!migrator.adapter().is_migrated(Version(10)));
+    assert!(!migrator.adapter().is_migrated(Version(20)));
+    migrator.up(None).unwrap();
+    migrator.down_namespace("auth", None).unwrap();
+    assert!(!migrator.adapter().is_migrated(Version(15)));
+    assert!(migrator.adapter().is_migrated(Version(10)));
+    assert!(migrator.adapter().is_migrated(Version(20)));
+}
+
+#[test]
+fn test_rollback_on_failure() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.adapter_mut().failing_applies.insert(Version(20));
+    assert!(migrator.up(None).is_err());
+    assert!(migrator.adapter().is_migrated(Version(10)));
+
     migrator.down(None).unwrap();
+    migrator.set_rollback_on_failure(true);
+    match migrator.up(None) {
+        Err(ref err) if err.migration_version() == Some(Version(20)) => {},
+        _ => panic!("expected `Error::Adapter`"),
+    }
+    assert_eq!(migrator.current_version().unwrap(), None);
+
+    migrator.adapter_mut().failing_reverts.insert(Version(10));
+    match migrator.up(None) {
+        Err(Error::RollbackFailed { apply_error, revert_error }) => {
+            assert_eq!(apply_error.migration_version(), Some(Version(20)));
+            assert_eq!(revert_error.migration_version(), Some(Version(10)));
+        },
+        _ => panic!("expected `Error::RollbackFailed`"),
+    }
+    assert!(migrator.adapter().is_migrated(Version(10)));
+}
+
+#[test]
+fn test_step() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    assert!(migrator.up_one().unwrap());
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(10)));
+    assert!(migrator.up_one().unwrap());
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(20)));
+    assert!(!migrator.up_one().unwrap());
+    assert!(migrator.down_one().unwrap());
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(10)));
+    assert!(migrator.down_one().unwrap());
     assert_eq!(migrator.current_version().unwrap(), None);
+    assert!(!migrator.down_one().unwrap());
+}
+
+#[test]
+fn test_progress_callback() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(Some(Version(10))).unwrap();
+
+    let recorded = events.clone();
+    migrator.set_progress_callback(move |event| {
+        recorded.lock().unwrap().push(match event {
+            ProgressEvent::BeforeApply { version, .. } => format!("before apply {}", version),
+            ProgressEvent::AfterApply { version, .. } => format!("after apply {}", version),
+            ProgressEvent::BeforeRevert { version, .. } => format!("before revert {}", version),
+            ProgressEvent::AfterRevert { version, .. } => format!("after revert {}", version),
+            ProgressEvent::MigrationSkipped { version, .. } => format!("skipped {}", version),
+        });
+    });
     migrator.up(None).unwrap();
-    assert_eq!(migrator.current_version().unwrap(), Some(20));
+    migrator.down(Some(Version(10))).unwrap();
+    assert_eq!(*events.lock().unwrap(), vec![
+        "skipped 10",
+        "before apply 20",
+        "after apply 20",
+        "before revert 20",
+        "after revert 20",
+    ]);
 }
 
 #[test]
-fn test_retroactive_migrations() {
+fn test_version_queries() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(Some(Version(10))).unwrap();
+    migrator.adapter_mut().versions.insert(Version(5));
+    assert_eq!(migrator.pending_versions().unwrap(), vec![Version(20)].into_iter().collect());
+    assert_eq!(migrator.applied_versions().unwrap(), vec![Version(10)].into_iter().collect());
+    assert_eq!(migrator.orphaned_versions().unwrap(), vec![Version(5)].into_iter().collect());
+}
+
+#[test]
+fn test_status() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(Some(Version(10))).unwrap();
+    migrator.adapter_mut().versions.insert(Version(5));
+    let status: Vec<(Version, String, MigrationState)> = migrator.status().unwrap().into_iter()
+        .map(|s| (s.version, s.description, s.state))
+        .collect();
+    assert_eq!(status, vec![
+        (Version(5), "<unregistered>".into(), MigrationState::Orphaned),
+        (Version(10), "first migration".into(), MigrationState::Applied),
+        (Version(20), "second migration".into(), MigrationState::Pending),
+    ]);
+}
+
+#[test]
+fn test_transactions() {
+    let mut migrator = Migrator::new_transactional(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.adapter_mut().failing_applies.insert(Version(20));
+    assert!(migrator.up(None).is_err());
+    assert_eq!(migrator.adapter().transaction_log, vec!["begin", "commit", "begin", "rollback"]);
+
+    // Non-transactional migrators never touch the transaction controls:
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.up(None).unwrap();
+    assert!(migrator.adapter().transaction_log.is_empty());
+}
+
+struct TimestampMigration;
+migration!(TimestampMigration, 20231015120000, "timestamped migration");
+
+#[test]
+fn test_validate_registered_versions() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(TimestampMigration)).unwrap();
+    assert_eq!(migrator.validate_registered_versions(), vec![Version(10)]);
+}
+
+#[test]
+fn test_reset() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(Some(Version(10))).unwrap();
+    migrator.reset_and_up().unwrap();
+    assert_eq!(migrator.migrated_versions().unwrap(), migrator.registered_versions());
+    migrator.reset().unwrap();
+    assert!(migrator.migrated_versions().unwrap().is_empty());
+    assert!(migrator.registered_versions().is_empty());
+}
+
+#[test]
+fn test_up_to_latest() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    assert!(migrator.is_up_to_date().unwrap());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    assert!(!migrator.is_up_to_date().unwrap());
+    migrator.up_to_latest().unwrap();
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(20)));
+    assert!(migrator.is_up_to_date().unwrap());
+}
+
+trait CloneableMigration: Migration {
+    fn clone_box(&self) -> Box<dyn CloneableMigration>;
+}
+
+impl<M: Migration + Clone + 'static> CloneableMigration for M {
+    fn clone_box(&self) -> Box<dyn CloneableMigration> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn CloneableMigration> {
+    fn clone(&self) -> Box<dyn CloneableMigration> {
+        (**self).clone_box()
+    }
+}
+
+#[derive(Clone)]
+struct CloneableAdapter {
+    versions: BTreeSet<Version>,
+}
+
+impl Adapter for CloneableAdapter {
+    type MigrationType = dyn CloneableMigration;
+    type Error = ();
+
+    fn migrated_versions(&mut self) -> Result<BTreeSet<Version>, ()> {
+        Ok(self.versions.clone())
+    }
+
+    fn apply_migration(&mut self, migration: &dyn CloneableMigration) -> Result<(), ()> {
+        self.versions.insert(migration.version());
+        Ok(())
+    }
+
+    fn revert_migration(&mut self, migration: &dyn CloneableMigration) -> Result<(), ()> {
+        self.versions.remove(&migration.version());
+        Ok(())
+    }
+}
+
+#[test]
+fn test_clone() {
+    let mut migrator = Migrator::new(CloneableAdapter { versions: BTreeSet::new() });
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(Some(Version(10))).unwrap();
+
+    let mut snapshot = migrator.clone();
+    migrator.up(None).unwrap();
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(20)));
+    assert_eq!(snapshot.current_version().unwrap(), Some(Version(10)));
+    assert_eq!(snapshot.registered_versions(), migrator.registered_versions());
+    assert_eq!(migrator.versions_applied_in_order().unwrap(), vec![Version(10), Version(20)]);
+}
+
+#[test]
+fn test_deregister() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(None).unwrap();
+    assert_eq!(migrator.deregister(Version(20)).map(|m| m.version()), Some(Version(20)));
+    assert!(migrator.deregister(Version(20)).is_none());
+    assert!(!migrator.version_registered(Version(20)));
+    assert!(migrator.adapter().is_migrated(Version(20)));
+    assert_eq!(migrator.orphaned_versions().unwrap(), vec![Version(20)].into_iter().collect());
+}
+
+struct FixedFirstMigration;
+migration!(FixedFirstMigration, 10, "first migration, fixed");
+
+#[test]
+fn test_replace() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    assert!(migrator.replace(Box::new(FirstMigration)).is_none());
+    let replaced = migrator.replace(Box::new(FixedFirstMigration)).unwrap();
+    assert_eq!(replaced.description(), "first migration");
+    assert_eq!(migrator.migration_count(), 1);
+    assert_eq!(migrator.find_migration(Version(10)).unwrap().description(),
+               "first migration, fixed");
+}
+
+#[test]
+fn test_counts() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    assert!(migrator.is_empty());
+    assert_eq!(migrator.migration_count(), 0);
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    assert!(!migrator.is_empty());
+    assert_eq!(migrator.migration_count(), 2);
+    migrator.up(Some(Version(10))).unwrap();
+    assert_eq!(migrator.applied_count().unwrap(), 1);
+    assert_eq!(migrator.pending_count().unwrap(), 1);
+}
+
+#[test]
+fn test_apply_and_revert_specific() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.apply_specific(Version(20)).unwrap();
+    assert!(migrator.adapter().is_migrated(Version(20)));
+    assert!(!migrator.adapter().is_migrated(Version(10)));
+    migrator.revert_specific(Version(20)).unwrap();
+    assert!(!migrator.adapter().is_migrated(Version(20)));
+
+    match migrator.apply_specific(Version(30)) {
+        Err(Error::VersionNotFound(Version(30))) => {},
+        _ => panic!("expected `Error::VersionNotFound(30)`"),
+    }
+    match migrator.revert_specific(Version(30)) {
+        Err(Error::VersionNotFound(Version(30))) => {},
+        _ => panic!("expected `Error::VersionNotFound(30)`"),
+    }
+}
+
+#[test]
+fn test_formatting() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(Some(Version(10))).unwrap();
+
+    let debug = format!("{:?}", migrator);
+    assert!(debug.contains("DummyAdapter"));
+    assert!(debug.contains("migrations: {10: \"first migration\", 20: \"second migration\"}"));
+
+    assert_eq!(migrator.to_string_with_state().unwrap(),
+               "10 [applied] first migration\n20 [pending] second migration\n");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_error_conversions() {
+    fn connect(fail: bool) -> Result<(), std::fmt::Error> {
+        if fail { Err(std::fmt::Error) } else { Ok(()) }
+    }
+
+    fn current_version(fail: bool) -> Result<Option<Version>, Error<std::fmt::Error>> {
+        connect(fail)?;
+        Ok(Some(Version(10)))
+    }
+
+    fn run(fail: bool) -> Result<Option<Version>, Box<dyn std::error::Error>> {
+        Ok(current_version(fail)?)
+    }
+
+    assert_eq!(current_version(false).unwrap(), Some(Version(10)));
+    match current_version(true) {
+        Err(Error::Adapter { context: ErrorContext::Adapter, error: std::fmt::Error }) => {},
+        _ => panic!("expected `Error::Adapter`"),
+    }
+    assert_eq!(run(false).unwrap(), Some(Version(10)));
+    assert!(run(true).unwrap_err().to_string().contains("error"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_error_source() {
+    use std::error::Error as StdError;
+
+    let err: Error<std::fmt::Error> = Error::adapter(std::fmt::Error);
+    assert!(err.source().is_some());
+    let err: Error<std::fmt::Error> = Error::VersionNotFound(Version(10));
+    assert!(err.source().is_none());
+}
+
+#[test]
+fn test_into_adapter() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.up(None).unwrap();
+    let adapter = migrator.into_adapter();
+    assert!(adapter.is_migrated(Version(10)));
+}
+
+#[test]
+fn test_take_adapter() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.up(None).unwrap();
+    let adapter = migrator.take_adapter();
+    assert!(adapter.is_migrated(Version(10)));
+    assert!(!migrator.adapter().is_migrated(Version(10)));
+    assert_eq!(migrator.migration_count(), 1);
+}
+
+#[test]
+fn test_builder() {
+    let migrator = Migrator::new(DummyAdapter::new())
+        .with_migration(Box::new(FirstMigration)).unwrap()
+        .with_migration(Box::new(SecondMigration)).unwrap();
+    assert_eq!(migrator.registered_versions(),
+               vec![Version(10), Version(20)].into_iter().collect());
+
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(FirstMigration),
+                                                   Box::new(SecondMigration)];
+    let migrator = Migrator::new(DummyAdapter::new()).with_migrations(migrations).unwrap();
+    assert_eq!(migrator.migration_count(), 2);
+
+    let duplicate = Migrator::new(DummyAdapter::new())
+        .with_migrations(vec![Box::new(FirstMigration) as Box<dyn Migration>,
+                              Box::new(FirstMigration)]);
+    assert_eq!(duplicate.err(), Some(RegistrationError::DuplicateVersion(Version(10))));
+}
+
+#[test]
+fn test_loaded_from() {
+    let migrator = Migrator::new(DummyAdapter::new()).loaded_from("migrations/schema.rs");
+    assert_eq!(migrator.source(), Some("migrations/schema.rs"));
+    assert_eq!(Migrator::new(DummyAdapter::new()).source(), None);
+}
+
+#[test]
+fn test_into_iterator() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.register(Box::new(FirstMigration)).unwrap();
+
+    let mut changelog = Vec::new();
+    for (version, migration) in &migrator {
+        changelog.push(format!("{}: {}", version, migration.description()));
+    }
+    assert_eq!(changelog, vec!["10: first migration", "20: second migration"]);
+    assert_eq!((&migrator).into_iter().next_back().map(|(&v, _)| v), Some(Version(20)));
+}
+
+#[test]
+fn test_error_predicates() {
     let mut migrator = Migrator::new(DummyAdapter::new());
-    migrator.register(Box::new(SecondMigration));
-    migrator.up(Some(20)).unwrap();
-    assert_eq!(migrator.current_version().unwrap(), Some(20));
-    assert!(migrator.adapter().is_migrated(20));
-    assert!(!migrator.adapter().is_migrated(10));
-    migrator.register(Box::new(FirstMigration));
-    migrator.up(Some(20)).unwrap();
-    assert_eq!(migrator.current_version().unwrap(), Some(20));
-    assert!(migrator.adapter().is_migrated(20));
-    assert!(migrator.adapter().is_migrated(10));
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.adapter_mut().failing_applies.insert(Version(10));
+    let err = migrator.up(None).unwrap_err();
+    assert!(err.is_migration_error());
+    assert!(!err.is_adapter_error());
+    assert_eq!(err.migration_version(), Some(Version(10)));
+    assert_eq!(err.migration_direction(), Some(&Direction::Up));
+
+    let err: Error<()> = Error::adapter(());
+    assert!(err.is_adapter_error());
+    assert!(!err.is_migration_error());
+    assert_eq!(err.migration_version(), None);
+    assert!(err.migration_direction().is_none());
+}
+
+#[test]
+fn test_ranges() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+
+    migrator.up_range(Version(12)..=Version(20)).unwrap();
+    assert_eq!(migrator.migrated_versions().unwrap(),
+               vec![Version(15), Version(20)].into_iter().collect());
+
+    migrator.up_range(Version(0)..=Version(10)).unwrap();
+    migrator.down_range(Version(15)..=Version(15)).unwrap();
+    assert_eq!(migrator.migrated_versions().unwrap(),
+               vec![Version(10), Version(20)].into_iter().collect());
+
+    migrator.down_range(Version(0)..=Version(100)).unwrap();
+    assert_eq!(migrator.current_version().unwrap(), None);
+}
+
+struct DataMigration;
+migration!(DataMigration, 25, "data migration", &["data"]);
+
+#[test]
+fn test_tags() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(DataMigration)).unwrap();
+    migrator.up_tagged(&["data"], None).unwrap();
+    assert!(migrator.adapter().is_migrated(Version(25)));
+    assert!(!migrator.adapter().is_migrated(Version(10)));
+    migrator.up(None).unwrap();
+    migrator.down_tagged(&["data", "schema"], None).unwrap();
+    assert!(!migrator.adapter().is_migrated(Version(25)));
+    assert!(migrator.adapter().is_migrated(Version(10)));
+}
+
+struct BackfillMigration;
+migration!(BackfillMigration, 30, "backfill migration", phase = Data);
+struct CleanupMigration;
+migration!(CleanupMigration, 40, "cleanup migration", phase = MigrationPhase::PostDeploy);
+
+#[test]
+fn test_phases() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(BackfillMigration)).unwrap();
+    migrator.register(Box::new(CleanupMigration)).unwrap();
+    assert_eq!(migrator.pending_in_phase(MigrationPhase::Data).unwrap(),
+               vec![Version(30)].into_iter().collect());
+
+    assert_eq!(migrator.up_phase(MigrationPhase::Schema).unwrap(), vec![Version(10)]);
+    assert_eq!(migrator.up_phase(MigrationPhase::PostDeploy).unwrap(), vec![Version(40)]);
+    assert!(!migrator.adapter().is_migrated(Version(30)));
+    assert_eq!(migrator.up_phase(MigrationPhase::Data).unwrap(), vec![Version(30)]);
+    assert!(migrator.pending_in_phase(MigrationPhase::Data).unwrap().is_empty());
+}
+
+struct SquashedMigration;
+migration!(SquashedMigration, 20, "squashed migration");
+
+#[test]
+fn test_squash() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+
+    assert_eq!(migrator.squash(Version(20), Version(10), Box::new(SquashedMigration)),
+               Err(SquashError::InvalidRange { from: Version(20), to: Version(10) }));
+    assert_eq!(migrator.squash(Version(10), Version(15), Box::new(SquashedMigration)),
+               Err(SquashError::VersionConflict(Version(20))));
+
+    migrator.squash(Version(10), Version(20), Box::new(SquashedMigration)).unwrap();
+    assert_eq!(migrator.registered_versions(), vec![Version(20)].into_iter().collect());
+    assert_eq!(migrator.to_string_with_state().unwrap(), "20 [pending] squashed migration\n");
+    assert!(migrator.adapter().versions.is_empty());
+}
+
+#[test]
+fn test_count_pending_and_applied() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(None).unwrap();
+    migrator.deregister(Version(20));
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    assert_eq!(migrator.count_pending().unwrap(), 1);
+    assert_eq!(migrator.count_applied().unwrap(), 1);
+    assert_eq!(migrator.applied_count().unwrap(), 2);
+}
+
+#[test]
+fn test_direction() {
+    assert_eq!(Direction::Up, Direction::Up);
+    assert_ne!(Direction::Up, Direction::Down);
+    assert!(Direction::Up.is_up());
+    assert!(!Direction::Up.is_down());
+    assert!(Direction::Down.is_down());
+
+    let directions: std::collections::HashSet<Direction> =
+        vec![Direction::Up, Direction::Down, Direction::Up].into_iter().collect();
+    assert_eq!(directions.len(), 2);
+}
+
+#[test]
+fn test_batch_hooks() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(Some(Version(10))).unwrap();
+    assert_eq!(migrator.adapter().batch_log, vec!["before", "success"]);
+
+    migrator.adapter_mut().failing_applies.insert(Version(20));
+    migrator.set_rollback_on_failure(true);
+    assert!(migrator.up(None).is_err());
+    migrator.down(None).unwrap();
+    assert_eq!(migrator.adapter().batch_log,
+               vec!["before", "success", "before", "failure", "before", "success"]);
+}
+
+#[test]
+fn test_find_migration() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(Some(Version(10))).unwrap();
+
+    let current = migrator.current_version().unwrap().unwrap();
+    assert_eq!(migrator.find_migration(current).map(|m| m.description()),
+               Some("first migration".into()));
+    assert!(migrator.find_migration(Version(30)).is_none());
+    assert_eq!(migrator.find_migration_mut(Version(20)).map(|m| m.version()), Some(Version(20)));
+    assert!(migrator.find_migration_mut(Version(30)).is_none());
+}
+
+#[test]
+fn test_snapshot() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    let snapshot = migrator.snapshot();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(None).unwrap();
+    migrator.restore(snapshot);
+    assert_eq!(migrator.registered_versions(), vec![Version(10)].into_iter().collect());
+    assert!(migrator.adapter().is_migrated(Version(20)));
+}
+
+#[test]
+fn test_versions_between() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    assert_eq!(migrator.versions_between(Version(10), Version(20)),
+               vec![Version(10), Version(15), Version(20)].into_iter().collect());
+    assert_eq!(migrator.versions_between(Version(11), Version(19)),
+               vec![Version(15)].into_iter().collect());
+    assert_eq!(migrator.versions_between(Version(20), Version(20)),
+               vec![Version(20)].into_iter().collect());
+    assert!(migrator.versions_between(Version(21), Version(30)).is_empty());
+    assert!(migrator.versions_between(Version(20), Version(10)).is_empty());
+}
+
+#[test]
+fn test_count_versions() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    assert_eq!(migrator.count_versions_after(Version(0)), 0);
+    assert_eq!(migrator.count_versions_before(Version(0)), 0);
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    assert_eq!(migrator.count_versions_after(Version(9)), 3);
+    assert_eq!(migrator.count_versions_after(Version(10)), 2);
+    assert_eq!(migrator.count_versions_after(Version(16)), 1);
+    assert_eq!(migrator.count_versions_after(Version(20)), 0);
+    assert_eq!(migrator.count_versions_before(Version(10)), 0);
+    assert_eq!(migrator.count_versions_before(Version(15)), 1);
+    assert_eq!(migrator.count_versions_before(Version(20)), 2);
+    assert_eq!(migrator.count_versions_before(Version(21)), 3);
+}
+
+#[test]
+fn test_state_partition() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(None).unwrap();
+    migrator.deregister(Version(20));
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    assert_eq!(migrator.applied_and_registered().unwrap(), vec![Version(10)].into_iter().collect());
+    assert_eq!(migrator.applied_but_not_registered().unwrap(),
+               vec![Version(20)].into_iter().collect());
+    assert_eq!(migrator.registered_but_not_applied().unwrap(),
+               vec![Version(15)].into_iter().collect());
+}
+
+#[test]
+fn test_max_version_gap() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.set_max_version_gap(Some(10));
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    let err = RegistrationError::VersionGapExceeded {
+        version: Version(25),
+        highest: Version(10),
+        max_gap: 10,
+    };
+    assert_eq!(migrator.register(Box::new(DataMigration)), Err(err));
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(DataMigration)).unwrap();
+
+    migrator.set_max_version_gap(None);
+    migrator.register(Box::new(TimestampMigration)).unwrap();
+}
+
+struct MinimumMigration;
+migration!(MinimumMigration, i64::MIN, "minimum migration");
+struct MaximumMigration;
+migration!(MaximumMigration, i64::MAX, "maximum migration");
+
+#[test]
+fn test_max_version_gap_overflow() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.set_max_version_gap(Some(10));
+    migrator.register(Box::new(MinimumMigration)).unwrap();
+    let err = RegistrationError::VersionGapExceeded {
+        version: Version(i64::MAX),
+        highest: Version(i64::MIN),
+        max_gap: 10,
+    };
+    assert_eq!(migrator.register(Box::new(MaximumMigration)), Err(err));
+}
+
+#[test]
+fn test_locking() {
+    let mut migrator = Migrator::new_locked(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(None).unwrap();
+    migrator.migrate_to(Some(Version(10))).unwrap();
+    assert_eq!(migrator.adapter().lock_log, vec!["acquire", "release", "acquire", "release"]);
+
+    migrator.adapter_mut().lock_contended = true;
+    match migrator.down(None) {
+        Err(Error::LockNotAcquired) => {},
+        _ => panic!("expected `Error::LockNotAcquired`"),
+    }
+    assert!(migrator.adapter().is_migrated(Version(10)));
+
+    // The lock is released even if the run fails:
+    migrator.adapter_mut().lock_contended = false;
+    migrator.adapter_mut().failing_applies.insert(Version(20));
+    assert!(migrator.up(None).is_err());
+    assert_eq!(migrator.adapter().lock_log.len(), 6);
+    assert_eq!(migrator.adapter().lock_log.last(), Some(&"release"));
+
+    // Unlocked migrators never touch the lock:
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.up(None).unwrap();
+    assert!(migrator.adapter().lock_log.is_empty());
+}
+
+#[test]
+fn test_migrate_exact() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+
+    let applied = Arc::new(Mutex::new(Vec::new()));
+    let log = applied.clone();
+    migrator.set_progress_callback(move |event| {
+        if let ProgressEvent::AfterApply { version, .. } = event {
+            log.lock().unwrap().push(version);
+        }
+    });
+
+    match migrator.migrate_exact(&[Version(20), Version(30)]) {
+        Err(Error::VersionNotFound(Version(30))) => {},
+        _ => panic!("expected `Error::VersionNotFound(30)`"),
+    }
+    assert_eq!(migrator.current_version().unwrap(), None);
+
+    migrator.apply_specific(Version(15)).unwrap();
+    migrator.migrate_exact(&[Version(20), Version(15), Version(10)]).unwrap();
+    assert_eq!(*applied.lock().unwrap(), vec![Version(15), Version(20), Version(10)]);
+}
+
+#[test]
+fn test_migrate_exact_duplicates() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.migrate_exact(&[Version(20), Version(10), Version(20)]).unwrap();
+    assert_eq!(migrator.adapter().applied_order, vec![Version(20), Version(10)]);
+}
+
+#[test]
+fn test_check_integrity() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(None).unwrap();
+    assert!(!migrator.check_integrity().unwrap().has_issues());
+
+    migrator.deregister(Version(20));
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    let report = migrator.check_integrity().unwrap();
+    assert!(report.has_issues());
+    assert_eq!(report, IntegrityReport {
+        orphaned: vec![Version(20)].into_iter().collect(),
+        pending: vec![Version(15)].into_iter().collect(),
+        applied: vec![Version(10)].into_iter().collect(),
+    });
+    assert_eq!(migrator.migrated_versions().unwrap(),
+               vec![Version(10), Version(20)].into_iter().collect());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_up_with_timeout() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.set_progress_callback(|event| {
+        if let ProgressEvent::AfterApply { .. } = event {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    });
+
+    match migrator.up_with_timeout(None, Duration::from_millis(10)) {
+        Err(Error::Timeout { elapsed, last_applied: Some(Version(10)) }) => {
+            assert!(elapsed > Duration::from_millis(10));
+        },
+        _ => panic!("expected `Error::Timeout`"),
+    }
+    assert!(migrator.adapter().is_migrated(Version(10)));
+    assert!(!migrator.adapter().is_migrated(Version(20)));
+
+    migrator.up_with_timeout(None, Duration::from_secs(60)).unwrap();
+    assert!(migrator.adapter().is_migrated(Version(20)));
+}
+
+#[test]
+fn test_error_combinators() {
+    let err: Error<i32> = Error::RollbackFailed {
+        apply_error: Box::new(Error::Adapter {
+            context: ErrorContext::Migration {
+                version: Version(20),
+                description: "second migration".into(),
+                label: None,
+                direction: Direction::Up,
+            },
+            error: 1,
+        }),
+        revert_error: Box::new(Error::adapter(2)),
+    };
+    match err.map_adapter_err(|code| format!("code {}", code)) {
+        Error::RollbackFailed { apply_error, revert_error } => {
+            assert_eq!(apply_error.into_migration_error(),
+                       Some((Version(20), "second migration".into(), Direction::Up,
+                             "code 1".into())));
+            assert_eq!(revert_error.into_adapter_error(), Some("code 2".into()));
+        },
+        _ => panic!("expected `Error::RollbackFailed`"),
+    }
+
+    let err: Error<i32> = Error::VersionNotFound(Version(10));
+    assert!(matches!(err.map_adapter_err(|code| code + 1), Error::VersionNotFound(Version(10))));
+    assert_eq!(Error::<i32>::DependencyCycle.into_adapter_error(), None);
+    assert_eq!(Error::adapter(1).into_migration_error(), None);
+}
+
+#[test]
+fn test_error_display_no_typos() {
+    let err: Error<&str> = Error::adapter("connection refused");
+    assert!(err.to_string().starts_with("Adapter error"));
+    assert_eq!(err.to_string(), "Adapter error: connection refused");
+}
+
+#[test]
+fn test_error_context() {
+    let context = ErrorContext::Migration {
+        version: Version(20),
+        description: "second migration".into(),
+        label: None,
+        direction: Direction::Down,
+    };
+    let err: Error<&str> =
+        Error::Adapter { context: context.clone(), error: "relation does not exist" };
+    assert_eq!(err.context(), Some(&context));
+    assert_eq!(err.adapter_error(), Some(&"relation does not exist"));
+    assert_eq!(err.to_string(),
+               "Error reverting migration 20 (second migration), error: relation does not exist");
+
+    let context = ErrorContext::Batch {
+        versions: vec![Version(20), Version(10)],
+        direction: Direction::Down,
+    };
+    let err: Error<&str> = Error::Adapter { context, error: "relation does not exist" };
+    assert_eq!(err.migration_version(), None);
+    assert_eq!(err.to_string(),
+               "Error reverting batch of migrations [20, 10], error: relation does not exist");
+
+    let err: Error<&str> = Error::adapter("connection refused");
+    assert_eq!(err.context(), Some(&ErrorContext::Adapter));
+    assert_eq!(err.adapter_error(), Some(&"connection refused"));
+    assert_eq!(Error::<&str>::LockNotAcquired.context(), None);
+}
+
+struct AddColumnMigration {
+    table: &'static str,
+    column: String,
+}
+
+impl Migration for AddColumnMigration {
+    fn version(&self) -> Version { Version(50) }
+    fn description(&self) -> String { format!("add column {} to {}", self.column, self.table) }
+}
+
+#[test]
+fn test_dynamic_description() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(AddColumnMigration { table: "users", column: "email".into() }))
+        .unwrap();
+    migrator.adapter_mut().failing_applies.insert(Version(50));
+    match migrator.up(None) {
+        Err(Error::Adapter { context: ErrorContext::Migration { description, .. }, .. }) => {
+            assert_eq!(description, "add column email to users");
+        },
+        _ => panic!("expected `Error::Adapter`"),
+    }
+}
+
+struct SqlAdapter {
+    statements: Vec<&'static str>,
+}
+
+impl Adapter for SqlAdapter {
+    type MigrationType = dyn ReversibleMigration;
+    type Error = ();
+
+    fn current_version(&mut self) -> Result<Option<Version>, ()> {
+        Ok(None)
+    }
+
+    fn migrated_versions(&mut self) -> Result<BTreeSet<Version>, ()> {
+        Ok(BTreeSet::new())
+    }
+
+    fn apply_migration(&mut self, migration: &dyn ReversibleMigration) -> Result<(), ()> {
+        self.statements.push(migration.up());
+        Ok(())
+    }
+
+    fn revert_migration(&mut self, migration: &dyn ReversibleMigration) -> Result<(), ()> {
+        self.statements.push(migration.down());
+        Ok(())
+    }
+}
+
+struct CreateUsers;
+reversible_migration!(CreateUsers, 10, "create users", "CREATE TABLE users;", "DROP TABLE users;");
+
+#[test]
+fn test_reversible_migration() {
+    let mut migrator = Migrator::new(SqlAdapter { statements: Vec::new() });
+    migrator.register(Box::new(CreateUsers)).unwrap();
+    migrator.apply_specific(Version(10)).unwrap();
+    migrator.revert_specific(Version(10)).unwrap();
+    assert_eq!(migrator.adapter().statements, vec!["CREATE TABLE users;", "DROP TABLE users;"]);
+}
+
+#[test]
+fn test_iter() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    let versions: Vec<Version> =
+        migrator.iter().map(|(&v, m)| Version(v.0 + m.version().0)).collect();
+    assert_eq!(versions, vec![Version(20), Version(40)]);
+    let versions: Vec<Version> = migrator.iter_mut().map(|(&v, _)| v).collect();
+    assert_eq!(versions, vec![Version(10), Version(20)]);
+    assert_eq!(migrator.iter_mut().len(), 2);
+}
+
+#[test]
+fn test_from_migrations() {
+    let mut migrations: BTreeMap<Version, Box<dyn Migration>> = BTreeMap::new();
+    migrations.insert(Version(10), Box::new(FirstMigration));
+    migrations.insert(Version(20), Box::new(SecondMigration));
+    let migrator = Migrator::from_migrations(DummyAdapter::new(), migrations).unwrap();
+    assert_eq!(migrator.registered_versions(),
+               vec![Version(10), Version(20)].into_iter().collect());
+
+    let mut migrations: BTreeMap<Version, Box<dyn Migration>> = BTreeMap::new();
+    migrations.insert(Version(10), Box::new(FirstMigration));
+    migrations.insert(Version(30), Box::new(SecondMigration));
+    assert_eq!(Migrator::from_migrations(DummyAdapter::new(), migrations).err(),
+               Some(RegistrationError::VersionMismatch { key: Version(30), version: Version(20) }));
+}
+
+#[test]
+fn test_extend() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.extend(vec![Box::new(FirstMigration) as Box<dyn Migration>,
+                         Box::new(SecondMigration),
+                         Box::new(FirstMigration)]);
+    assert_eq!(migrator.registered_versions(),
+               vec![Version(10), Version(20)].into_iter().collect());
+
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(FirstMigration),
+                                                   Box::new(SecondMigration)];
+    let migrator: Migrator<DummyAdapter> = migrations.into_iter().collect();
+    assert_eq!(migrator.migration_count(), 2);
+
+    let migrator = Migrator::with_adapter_and_iter(DummyAdapter::new(),
+                                                   vec![Box::new(AuthMigration) as Box<_>]);
+    assert_eq!(migrator.registered_versions(), vec![Version(15)].into_iter().collect());
+}
+
+#[test]
+fn test_default() {
+    let mut migrator: Migrator<DummyAdapter> = Default::default();
+    assert_eq!(migrator.migration_count(), 0);
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.up(None).unwrap();
+    assert!(migrator.adapter().is_migrated(Version(10)));
+}
+
+#[test]
+fn test_descending_sort_order() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.set_sort_order(SortOrder::Descending);
+
+    let executed = Arc::new(Mutex::new(Vec::new()));
+    let log = executed.clone();
+    migrator.set_progress_callback(move |event| {
+        match event {
+            ProgressEvent::AfterApply { version, .. } |
+            ProgressEvent::AfterRevert { version, .. } => log.lock().unwrap().push(version),
+            _ => {},
+        }
+    });
+
+    migrator.up(Some(Version(15))).unwrap();
+    assert_eq!(*executed.lock().unwrap(), vec![Version(20), Version(15)]);
+    assert!(!migrator.is_up_to_date().unwrap());
+    migrator.up(None).unwrap();
+    assert!(migrator.is_up_to_date().unwrap());
+    migrator.down(Some(Version(15))).unwrap();
+    assert!(!migrator.adapter().is_migrated(Version(10)));
+    assert!(migrator.adapter().is_migrated(Version(15)));
+    migrator.down(None).unwrap();
+    assert_eq!(*executed.lock().unwrap(),
+               vec![Version(20), Version(15), Version(10), Version(10), Version(15), Version(20)]);
+}
+
+// Orders the versions that are multiples of ten before all others.
+struct RoundFirst;
+
+impl VersionComparator for RoundFirst {
+    fn compare(&self, a: Version, b: Version) -> Ordering {
+        (a.0 % 10, a).cmp(&(b.0 % 10, b))
+    }
+}
+
+#[test]
+fn test_custom_comparator() {
+    let mut migrator = Migrator::new_with_comparator(DummyAdapter::new(), RoundFirst);
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+
+    let executed = Arc::new(Mutex::new(Vec::new()));
+    let log = executed.clone();
+    migrator.set_progress_callback(move |event| {
+        match event {
+            ProgressEvent::AfterApply { version, .. } |
+            ProgressEvent::AfterRevert { version, .. } => log.lock().unwrap().push(version),
+            _ => {},
+        }
+    });
+
+    migrator.up(Some(Version(20))).unwrap();
+    assert_eq!(*executed.lock().unwrap(), vec![Version(10), Version(20)]);
+    assert!(!migrator.is_up_to_date().unwrap());
+    migrator.up(None).unwrap();
+    assert!(migrator.is_up_to_date().unwrap());
+    migrator.down(Some(Version(20))).unwrap();
+    assert!(!migrator.adapter().is_migrated(Version(15)));
+    assert!(migrator.adapter().is_migrated(Version(20)));
+    migrator.down(None).unwrap();
+    assert_eq!(*executed.lock().unwrap(),
+               vec![Version(10), Version(20), Version(15), Version(15), Version(20), Version(10)]);
+}
+
+#[test]
+fn test_register_migrations() {
+    assert_eq!(FirstMigration::VERSION, FirstMigration.version());
+
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    register_migrations!(migrator, [SecondMigration, FirstMigration]).unwrap();
+    assert_eq!(migrator.registered_versions(),
+               vec![Version(10), Version(20)].into_iter().collect());
+
+    match register_migrations!(migrator, [DataMigration, FirstMigration]) {
+        Err(RegistrationError::DuplicateVersion(Version(10))) => {},
+        _ => panic!("expected `RegistrationError::DuplicateVersion(10)`"),
+    }
+    assert!(migrator.version_registered(Version(25)));
+
+    // The migrator expression is evaluated only once:
+    let mut migrators = [Migrator::new(DummyAdapter::new()), Migrator::new(DummyAdapter::new())];
+    let mut iter = migrators.iter_mut();
+    register_migrations!(iter.next().unwrap(), [FirstMigration, SecondMigration]).unwrap();
+    assert_eq!(migrators[0].migration_count(), 2);
+    assert_eq!(migrators[1].migration_count(), 0);
+    register_migrations!(Migrator::new(DummyAdapter::new()), []).unwrap();
+}
+
+#[test]
+fn test_explain() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(Some(Version(10))).unwrap();
+    migrator.adapter_mut().versions.insert(Version(5));
+
+    assert_eq!(migrator.explain_up(None).unwrap(), "\
+| Version | Description      | Status  |
+| ------- | ---------------- | ------- |
+| 10      | first migration  | applied |
+| 20      | second migration | pending |
+");
+    assert_eq!(migrator.explain_status().unwrap(), "\
+| Version | Description      | Status   |
+| ------- | ---------------- | -------- |
+| 5       | <unregistered>   | orphaned |
+| 10      | first migration  | applied  |
+| 20      | second migration | pending  |
+");
+}
+
+#[test]
+fn test_down_migrations_unsupported() {
+    let mut migrator = Migrator::new(DummyAdapter { up_only: true, ..DummyAdapter::new() });
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(None).unwrap();
+
+    match migrator.down(Some(Version(10))) {
+        Err(Error::DownMigrationsUnsupported) => {},
+        _ => panic!("expected `Error::DownMigrationsUnsupported`"),
+    }
+    match migrator.down_one() {
+        Err(Error::DownMigrationsUnsupported) => {},
+        _ => panic!("expected `Error::DownMigrationsUnsupported`"),
+    }
+    assert!(migrator.adapter().is_migrated(Version(10)));
+    assert!(migrator.adapter().is_migrated(Version(20)));
+    assert_eq!(migrator.adapter().batch_log, vec!["before", "success"]);
+}
+
+#[test]
+fn test_index() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    assert_eq!(migrator[Version(20)].description(), "second migration");
+    assert_eq!(migrator.migration_at(0).unwrap().version(), Version(10));
+    assert_eq!(migrator.migration_at(1).unwrap().version(), Version(20));
+    assert!(migrator.migration_at(2).is_none());
+    assert_eq!(migrator[0].version(), Version(10));
+    assert_eq!(migrator[1].description(), "second migration");
+}
+
+#[test]
+#[should_panic(expected = "no migration registered with version 30")]
+fn test_index_unregistered() {
+    let migrator = Migrator::new(DummyAdapter::new());
+    migrator[Version(30)].version();
+}
+
+#[test]
+#[should_panic(expected = "no migration registered at position 1")]
+fn test_index_out_of_bounds() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator[1].version();
+}
+
+#[test]
+fn test_register_many() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+
+    match migrator.register_many(vec![Box::new(AuthMigration),
+                                      Box::new(SecondMigration),
+                                      Box::new(AuthMigration)]) {
+        Err((RegistrationError::DuplicateVersion(Version(15)), migrations)) => {
+            assert_eq!(migrations.len(), 3);
+        },
+        _ => panic!("expected `RegistrationError::DuplicateVersion(15)`"),
+    }
+    assert_eq!(migrator.registered_versions(), vec![Version(10)].into_iter().collect());
+
+    match migrator.register_many(vec![Box::new(AuthMigration), Box::new(FirstMigration)]) {
+        Err((RegistrationError::DuplicateVersion(Version(10)), _)) => {},
+        _ => panic!("expected `RegistrationError::DuplicateVersion(10)`"),
+    }
+
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(SecondMigration),
+                                                   Box::new(AuthMigration)];
+    assert!(migrator.register_many(migrations).is_ok());
+    assert_eq!(migrator.registered_versions(),
+               vec![Version(10), Version(15), Version(20)].into_iter().collect());
+}
+
+struct LabeledMigration;
+migration!(LabeledMigration, 40, "create users table", &[], "create_users_table");
+
+#[test]
+fn test_label() {
+    let mut adapter = DummyAdapter::new();
+    adapter.failing_applies.insert(Version(40));
+    let mut migrator = Migrator::new(adapter);
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(LabeledMigration)).unwrap();
+
+    assert_eq!(migrator.explain_up(None).unwrap(), "\
+| Version | Description                              | Status  |
+| ------- | ---------------------------------------- | ------- |
+| 10      | first migration                          | pending |
+| 40      | 'create_users_table': create users table | pending |
+");
+
+    let err = migrator.up(None).unwrap_err().map_adapter_err(|()| "failed");
+    assert_eq!(err.to_string(),
+               "Error applying migration 'create_users_table' (v40), error: failed");
+}
+
+#[test]
+fn test_verify_order() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.verify_order().unwrap();
+    migrator.up(Some(Version(15))).unwrap();
+    migrator.verify_order().unwrap();
+
+    migrator.revert_specific(Version(10)).unwrap();
+    migrator.apply_specific(Version(20)).unwrap();
+    migrator.adapter_mut().versions.insert(Version(5));
+    match migrator.verify_order() {
+        Err(Error::OrderViolation { gaps, unexpected }) => {
+            assert_eq!(gaps, vec![Version(10)]);
+            assert_eq!(unexpected, vec![Version(5)]);
+        },
+        _ => panic!("expected `Error::OrderViolation`"),
+    }
+}
+
+#[test]
+fn test_up_dry_run_diff() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    assert_eq!(migrator.up_dry_run_diff(Some(Version(10))).unwrap(), MigrationDiff {
+        to_apply: vec![Version(10)],
+        already_applied: vec![],
+        final_current_version: Some(Version(10)),
+    });
+
+    migrator.apply_specific(Version(15)).unwrap();
+    assert_eq!(migrator.up_dry_run_diff(None).unwrap(), MigrationDiff {
+        to_apply: vec![Version(10), Version(20)],
+        already_applied: vec![Version(15)],
+        final_current_version: Some(Version(20)),
+    });
+    assert!(!migrator.adapter().is_migrated(Version(10)));
+}
+
+#[test]
+fn test_retry_policy() {
+    let mut adapter = DummyAdapter::new();
+    adapter.flaky_applies.insert(Version(10), 2);
+    adapter.failing_applies.insert(Version(20));
+    let mut migrator = Migrator::new_transactional(adapter);
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.set_retry_policy(RetryPolicy {
+        max_attempts: 3,
+        backoff: BackoffStrategy::Fixed(Duration::from_millis(1)),
+    });
+
+    migrator.up(Some(Version(10))).unwrap();
+    assert!(migrator.adapter().is_migrated(Version(10)));
+    assert_eq!(migrator.adapter().transaction_log,
+               vec!["begin", "rollback", "begin", "rollback", "begin", "commit"]);
+
+    let err = migrator.up(None).unwrap_err();
+    assert!(matches!(err, Error::MaxRetriesExceeded { attempts: 3, last_error: (), .. }));
+    assert!(err.is_migration_error());
+    assert_eq!(err.migration_version(), Some(Version(20)));
+    assert_eq!(err.migration_direction(), Some(&Direction::Up));
+    assert_eq!(err.context(), Some(&ErrorContext::Migration {
+        version: Version(20),
+        description: "second migration".into(),
+        label: None,
+        direction: Direction::Up,
+    }));
+    assert_eq!(err.into_migration_error(),
+               Some((Version(20), "second migration".into(), Direction::Up, ())));
+    assert!(!migrator.adapter().is_migrated(Version(20)));
+}
+
+#[test]
+fn test_retry_policy_exhausted() {
+    let mut adapter = DummyAdapter::new();
+    adapter.failing_applies.insert(Version(10));
+    let mut migrator = Migrator::new_transactional(adapter);
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.set_retry_policy(RetryPolicy {
+        max_attempts: 3,
+        backoff: BackoffStrategy::Fixed(Duration::from_millis(1)),
+    });
+
+    // Every failed attempt is rolled back exactly once:
+    match migrator.up(None) {
+        Err(err @ Error::MaxRetriesExceeded { attempts: 3, last_error: (), .. }) => {
+            assert_eq!(err.migration_version(), Some(Version(10)));
+        },
+        _ => panic!("expected `Error::MaxRetriesExceeded`"),
+    }
+    assert_eq!(migrator.adapter().transaction_log,
+               vec!["begin", "rollback", "begin", "rollback", "begin", "rollback"]);
+
+    // A failed rollback ends the run without being attempted again:
+    migrator.adapter_mut().transaction_log.clear();
+    migrator.adapter_mut().failing_rollbacks = true;
+    match migrator.up(None) {
+        Err(Error::RollbackFailed { .. }) => {},
+        _ => panic!("expected `Error::RollbackFailed`"),
+    }
+    assert_eq!(migrator.adapter().transaction_log, vec!["begin", "rollback"]);
+    assert!(!migrator.adapter().is_migrated(Version(10)));
+}
+
+#[test]
+fn test_exponential_backoff() {
+    let backoff = BackoffStrategy::Exponential {
+        base: Duration::from_millis(100),
+        max: Duration::from_millis(350),
+    };
+    assert_eq!(backoff.delay(1), Duration::from_millis(100));
+    assert_eq!(backoff.delay(2), Duration::from_millis(200));
+    assert_eq!(backoff.delay(3), Duration::from_millis(350));
+    assert_eq!(backoff.delay(40), Duration::from_millis(350));
+}
+
+#[test]
+fn test_up_fill_gaps() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.up_fill_gaps().unwrap();
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(DataMigration)).unwrap();
+    migrator.register(Box::new(SquashedMigration)).unwrap();
+    migrator.apply_specific(Version(20)).unwrap();
+
+    migrator.up_fill_gaps().unwrap();
+    assert!(migrator.adapter().is_migrated(Version(10)));
+    assert!(migrator.adapter().is_migrated(Version(15)));
+    assert!(!migrator.adapter().is_migrated(Version(25)));
+}
+
+#[test]
+fn test_seed_versions() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+
+    match migrator.seed_versions(vec![Version(10), Version(30)].into_iter().collect()) {
+        Err(Error::VersionNotFound(Version(30))) => {},
+        _ => panic!("expected `Error::VersionNotFound(30)`"),
+    }
+    assert_eq!(migrator.current_version().unwrap(), None);
+
+    migrator.seed_versions(vec![Version(10)].into_iter().collect()).unwrap();
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(10)));
+    assert_eq!(migrator.pending_versions().unwrap(), vec![Version(20)].into_iter().collect());
+    assert!(migrator.adapter().batch_log.is_empty());
+}
+
+#[test]
+fn test_shared_migrations() {
+    let shared: Vec<Arc<dyn Migration>> = vec![Arc::new(FirstMigration), Arc::new(DataMigration)];
+    let mut primary = Migrator::new(DummyAdapter::new());
+    let mut replica = Migrator::new(DummyAdapter::new());
+    for migration in &shared {
+        primary.register(Box::new(migration.clone())).unwrap();
+        replica.register(Box::new(migration.clone())).unwrap();
+    }
+    assert_eq!(Arc::strong_count(&shared[0]), 3);
+
+    primary.up(None).unwrap();
+    replica.up(Some(Version(10))).unwrap();
+    assert_eq!(primary.registered_versions(), vec![Version(10), Version(25)].into_iter().collect());
+    assert_eq!(primary[Version(25)].tags(), &["data"]);
+    assert!(primary.adapter().is_migrated(Version(25)));
+    assert!(!replica.adapter().is_migrated(Version(25)));
+}
+
+struct AttributedMigration;
+migration!(AttributedMigration, 45, "add index", author = "alice", ticket = "JIRA-123",
+           tags = &["schema"]);
+
+#[test]
+fn test_author_and_ticket() {
+    assert_eq!(AttributedMigration.author(), Some("alice"));
+    assert_eq!(AttributedMigration.ticket(), Some("JIRA-123"));
+    assert_eq!(AttributedMigration.tags(), &["schema"]);
+    assert_eq!(FirstMigration.author(), None);
+
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(AttributedMigration)).unwrap();
+    assert_eq!(migrator.explain_status().unwrap(), "\
+| Version | Description                                 | Status  |
+| ------- | ------------------------------------------- | ------- |
+| 45      | add index (author: alice, ticket: JIRA-123) | pending |
+");
+}
+
+#[test]
+fn test_up_verbose() {
+    let mut adapter = DummyAdapter::new();
+    adapter.failing_applies.insert(Version(25));
+    let mut migrator = Migrator::new(adapter);
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(DataMigration)).unwrap();
+
+    let versions = |timings: &[(Version, Duration)]| {
+        timings.iter().map(|&(version, _)| version).collect::<Vec<_>>()
+    };
+    match migrator.up_verbose(None) {
+        Err((timings, ref err)) if err.migration_version() == Some(Version(25)) => {
+            assert_eq!(versions(&timings), vec![Version(10), Version(15)]);
+        },
+        _ => panic!("expected `Error::Adapter`"),
+    }
+
+    migrator.adapter_mut().failing_applies.clear();
+    assert_eq!(versions(&migrator.up_verbose(None).unwrap()), vec![Version(25)]);
+}
+
+#[test]
+fn test_migrate_to_latest_registered() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.migrate_to_latest_registered().unwrap();
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.migrate_to_latest_registered().unwrap();
+    assert!(migrator.adapter().is_migrated(Version(10)));
+    assert!(migrator.adapter().is_migrated(Version(20)));
+    assert!(migrator.is_up_to_date().unwrap());
+}
+
+#[test]
+fn test_versions_applied_in_order() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(None).unwrap();
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.up(None).unwrap();
+    assert_eq!(migrator.versions_applied_in_order().unwrap(),
+               vec![Version(20), Version(10), Version(15)]);
+    migrator.revert_specific(Version(10)).unwrap();
+    assert_eq!(migrator.versions_applied_in_order().unwrap(), vec![Version(20), Version(15)]);
+}
+
+#[test]
+fn test_fake_up() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    migrator.up(Some(Version(10))).unwrap();
+
+    assert_eq!(migrator.fake_up(Some(Version(15))).unwrap(), vec![Version(15)]);
+    assert_eq!(migrator.adapter().changelog, vec!["applied 10: first migration"]);
+    assert_eq!(migrator.fake_up(None).unwrap(), vec![Version(20)]);
+    assert!(migrator.is_up_to_date().unwrap());
+    assert!(migrator.fake_up(None).unwrap().is_empty());
+}
+
+#[test]
+fn test_peek_next() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    assert!(migrator.peek_next_up().unwrap().is_none());
+    assert!(migrator.peek_next_down().unwrap().is_none());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+
+    assert_eq!(migrator.peek_next_up().unwrap().map(|m| m.version()), Some(Version(10)));
+    assert!(migrator.peek_next_down().unwrap().is_none());
+    migrator.up_one().unwrap();
+    assert_eq!(migrator.peek_next_up().unwrap().map(|m| m.version()), Some(Version(20)));
+    assert_eq!(migrator.peek_next_down().unwrap().map(|m| m.version()), Some(Version(10)));
+    migrator.up_one().unwrap();
+    assert!(migrator.peek_next_up().unwrap().is_none());
+    assert_eq!(migrator.peek_next_down().unwrap().map(|m| m.version()), Some(Version(20)));
+    assert!(migrator.adapter().is_migrated(Version(20)));
+}
+
+#[test]
+fn test_run() {
+    let mut migrator = Migrator::new(DummyAdapter::new());
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    let applied = |migrator: &Migrator<DummyAdapter>| {
+        [Version(10), Version(15), Version(20)].iter()
+            .filter(|&&v| migrator.adapter().is_migrated(v))
+            .count()
+    };
+
+    migrator.run(MigrationCommand::Up(Some(Version(10)))).unwrap();
+    assert_eq!(applied(&migrator), 1);
+    migrator.run(MigrationCommand::Step(1)).unwrap();
+    assert!(migrator.adapter().is_migrated(Version(15)));
+    migrator.run(MigrationCommand::Step(5)).unwrap();
+    assert_eq!(applied(&migrator), 3);
+    migrator.run(MigrationCommand::Step(-2)).unwrap();
+    assert_eq!(migrator.current_version().unwrap(), Some(Version(10)));
+    migrator.run(MigrationCommand::Step(0)).unwrap();
+    assert_eq!(applied(&migrator), 1);
+    migrator.run(MigrationCommand::UpToLatest).unwrap();
+    assert_eq!(applied(&migrator), 3);
+    migrator.run(MigrationCommand::Down(None)).unwrap();
+    assert_eq!(applied(&migrator), 0);
+    migrator.run(MigrationCommand::Step(-1)).unwrap();
+    migrator.run(MigrationCommand::Reset).unwrap();
+    assert_eq!(applied(&migrator), 3);
+}
+
+#[test]
+fn test_up_until_error() {
+    let mut adapter = DummyAdapter::new();
+    adapter.failing_applies.insert(Version(25));
+    let mut migrator = Migrator::new(adapter);
+    migrator.set_rollback_on_failure(true);
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(DataMigration)).unwrap();
+
+    match migrator.up_until_error(None) {
+        (applied, Some(ref err)) if err.migration_version() == Some(Version(25)) => {
+            assert_eq!(applied, vec![Version(10), Version(15)]);
+        },
+        _ => panic!("expected `Error::Adapter`"),
+    }
+    assert!(migrator.adapter().is_migrated(Version(15)));
+
+    migrator.adapter_mut().failing_applies.clear();
+    match migrator.up_until_error(None) {
+        (applied, None) => assert_eq!(applied, vec![Version(25)]),
+        _ => panic!("expected no error"),
+    }
+}
+
+#[test]
+fn test_migration_hooks() {
+    let mut adapter = DummyAdapter::new();
+    adapter.failing_applies.insert(Version(20));
+    let mut migrator = Migrator::new(adapter);
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+    assert!(migrator.up(None).is_err());
+    migrator.down(None).unwrap();
+    assert_eq!(migrator.adapter().changelog,
+               vec!["applied 10: first migration", "reverted 10: first migration"]);
+}
+
+#[test]
+fn test_batches() {
+    let mut adapter = DummyAdapter::new();
+    adapter.batching = true;
+    let mut migrator = Migrator::new_transactional(adapter);
+    migrator.register(Box::new(FirstMigration)).unwrap();
+    migrator.register(Box::new(AuthMigration)).unwrap();
+    migrator.register(Box::new(SecondMigration)).unwrap();
+
+    assert_eq!(migrator.up(None).unwrap(), vec![Version(10), Version(15), Version(20)]);
+    assert_eq!(migrator.down(Some(Version(10))).unwrap(), vec![Version(20), Version(15)]);
+    assert_eq!(migrator.adapter().batches, vec![
+        (Direction::Up, vec![Version(10), Version(15), Version(20)]),
+        (Direction::Down, vec![Version(20), Version(15)]),
+    ]);
+    assert_eq!(migrator.adapter().transaction_log, vec!["begin", "commit", "begin", "commit"]);
+    assert_eq!(migrator.adapter().changelog.len(), 5);
+
+    // A failed batch is rolled back as a whole:
+    migrator.adapter_mut().failing_applies.insert(Version(20));
+    let err = migrator.up(None).unwrap_err();
+    let versions = vec![Version(15), Version(20)];
+    let context = ErrorContext::Batch { versions, direction: Direction::Up };
+    assert_eq!(err.context(), Some(&context));
+    assert_eq!(migrator.adapter().transaction_log.last(), Some(&"rollback"));
+
+    // Rolling back on failure requires the migrations to be run one by one:
+    migrator.set_rollback_on_failure(true);
+    assert!(migrator.up(None).is_err());
+    assert_eq!(migrator.adapter().batches.len(), 3);
 }
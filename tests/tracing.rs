@@ -0,0 +1,137 @@
+#![cfg(all(feature = "tracing", feature = "memory"))]
+
+#[macro_use]
+extern crate schemamama;
+
+use schemamama::{Adapter, InMemoryAdapter, Migration, Migrator, Version};
+use std::collections::BTreeSet;
+use std::convert::Infallible;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+// Records the fields of every created span, and the innermost span in which each event was
+// emitted.
+#[derive(Clone, Default)]
+struct CapturingSubscriber {
+    spans: Arc<Mutex<Vec<String>>>,
+    events: Arc<Mutex<Vec<Option<u64>>>>,
+    entered: Arc<Mutex<Vec<u64>>>,
+}
+
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.push_str(&format!("{}={:?} ", field.name(), value));
+    }
+}
+
+impl Subscriber for CapturingSubscriber {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes) -> Id {
+        let mut fields = Fields(format!("{}: ", span.metadata().name()));
+        span.record(&mut fields);
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(fields.0.trim_end().to_string());
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _: &Id, _: &Record) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event) {
+        let current = self.entered.lock().unwrap().last().copied();
+        self.events.lock().unwrap().push(current);
+    }
+
+    fn enter(&self, span: &Id) {
+        self.entered.lock().unwrap().push(span.into_u64());
+    }
+
+    fn exit(&self, _: &Id) {
+        self.entered.lock().unwrap().pop();
+    }
+}
+
+// An in-memory adapter that runs migrations in batches.
+struct BatchAdapter {
+    versions: BTreeSet<Version>,
+}
+
+impl Adapter for BatchAdapter {
+    type MigrationType = dyn Migration;
+    type Error = Infallible;
+
+    fn current_schema_version(&mut self) -> Result<Option<Version>, Infallible> {
+        Ok(self.versions.iter().next_back().copied())
+    }
+
+    fn migrated_versions(&mut self) -> Result<BTreeSet<Version>, Infallible> {
+        Ok(self.versions.clone())
+    }
+
+    fn apply_migration(&mut self, migration: &dyn Migration) -> Result<(), Infallible> {
+        self.versions.insert(migration.version());
+        Ok(())
+    }
+
+    fn revert_migration(&mut self, migration: &dyn Migration) -> Result<(), Infallible> {
+        self.versions.remove(&migration.version());
+        Ok(())
+    }
+
+    fn supports_batch(&self) -> bool {
+        true
+    }
+}
+
+struct FirstMigration;
+migration!(FirstMigration, 10, "first migration");
+struct SecondMigration;
+migration!(SecondMigration, 20, "second migration");
+
+#[test]
+fn test_migration_spans() {
+    let subscriber = CapturingSubscriber::default();
+    tracing::subscriber::with_default(subscriber.clone(), || {
+        let mut migrator = Migrator::new(InMemoryAdapter::new());
+        migrator.register(Box::new(FirstMigration)).unwrap();
+        migrator.up(None).unwrap();
+        migrator.down(None).unwrap();
+    });
+
+    assert_eq!(*subscriber.spans.lock().unwrap(), vec![
+        "migration: version=10 description=first migration direction=Up",
+        "migration: version=10 description=first migration direction=Down",
+    ]);
+    assert_eq!(*subscriber.events.lock().unwrap(), vec![Some(1), Some(2)]);
+}
+
+#[test]
+fn test_batch_spans() {
+    let subscriber = CapturingSubscriber::default();
+    tracing::subscriber::with_default(subscriber.clone(), || {
+        let mut migrator = Migrator::new(BatchAdapter { versions: BTreeSet::new() });
+        migrator.register(Box::new(FirstMigration)).unwrap();
+        migrator.register(Box::new(SecondMigration)).unwrap();
+        migrator.up(None).unwrap();
+        migrator.down(None).unwrap();
+    });
+
+    assert_eq!(*subscriber.spans.lock().unwrap(), vec![
+        "migration_batch: versions=[10, 20] direction=Up",
+        "migration: version=10 description=first migration direction=Up",
+        "migration: version=20 description=second migration direction=Up",
+        "migration_batch: versions=[20, 10] direction=Down",
+        "migration: version=20 description=second migration direction=Down",
+        "migration: version=10 description=first migration direction=Down",
+    ]);
+    assert_eq!(*subscriber.events.lock().unwrap(), vec![Some(2), Some(3), Some(5), Some(6)]);
+}